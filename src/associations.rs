@@ -0,0 +1,417 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+const BUILTIN: &str = include_str!("associations.json");
+const USER_FILE: &str = "associations.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Class {
+    Scout,
+    Soldier,
+    Pyro,
+    Demoman,
+    Heavy,
+    Engineer,
+    Medic,
+    Sniper,
+    Spy,
+    All,
+}
+
+impl Class {
+    pub const ALL: [Class; 10] = [
+        Self::Scout,
+        Self::Soldier,
+        Self::Pyro,
+        Self::Demoman,
+        Self::Heavy,
+        Self::Engineer,
+        Self::Medic,
+        Self::Sniper,
+        Self::Spy,
+        Self::All,
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Scout => "Scout",
+            Self::Soldier => "Soldier",
+            Self::Pyro => "Pyro",
+            Self::Demoman => "Demoman",
+            Self::Heavy => "Heavy",
+            Self::Engineer => "Engineer",
+            Self::Medic => "Medic",
+            Self::Sniper => "Sniper",
+            Self::Spy => "Spy",
+            Self::All => "All",
+        }
+    }
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+impl TryFrom<&str> for Class {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.to_str() == s)
+            .ok_or_else(|| anyhow!("Unknown class `{}`", s))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Slot {
+    Primary,
+    Secondary,
+    Melee,
+    Pda,
+    /// The engineer's destruction PDA and the spy's watch, on the 5 key.
+    Pda2,
+    Other,
+}
+
+impl Slot {
+    pub const ALL: [Slot; 6] = [
+        Self::Primary,
+        Self::Secondary,
+        Self::Melee,
+        Self::Pda,
+        Self::Pda2,
+        Self::Other,
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Primary => "Primary",
+            Self::Secondary => "Secondary",
+            Self::Melee => "Melee",
+            Self::Pda => "PDA",
+            Self::Pda2 => "PDA2",
+            Self::Other => "Other",
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Self::Primary => 1,
+            Self::Secondary => 2,
+            Self::Melee => 3,
+            Self::Pda => 4,
+            Self::Pda2 => 5,
+            Self::Other => 9,
+        }
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+impl TryFrom<u8> for Slot {
+    type Error = anyhow::Error;
+
+    fn try_from(n: u8) -> Result<Self> {
+        match n {
+            1 => Ok(Self::Primary),
+            2 => Ok(Self::Secondary),
+            3 => Ok(Self::Melee),
+            4 => Ok(Self::Pda),
+            5 => Ok(Self::Pda2),
+            9 => Ok(Self::Other),
+            _ => bail!("Unknown slot `{}`", n),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Association {
+    pub class: Class,
+    pub slot: Slot,
+    pub display: String,
+    pub all: Vec<String>,
+}
+
+impl Association {
+    fn from_json(value: &json::JsonValue) -> Result<Self> {
+        if !value.is_object() {
+            bail!("Expected an object");
+        }
+
+        let class = value["class"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Missing or invalid `class`"))?;
+        let class = Class::try_from(class)?;
+
+        let slot = value["slot"]
+            .as_u8()
+            .ok_or_else(|| anyhow!("Missing or invalid `slot`"))?;
+        let slot = Slot::try_from(slot)?;
+
+        let display = value["display"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Missing or invalid `display`"))?
+            .to_string();
+
+        if !value["all"].is_array() {
+            bail!("Missing or invalid `all`");
+        }
+
+        let all = value["all"]
+            .members()
+            .enumerate()
+            .map(|(i, e)| {
+                e.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("`all[{}]` isn't a string", i))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            class,
+            slot,
+            display,
+            all,
+        })
+    }
+
+    fn to_json(&self) -> json::JsonValue {
+        let mut value = json::JsonValue::new_object();
+        value["class"] = self.class.to_str().into();
+        value["slot"] = self.slot.to_u8().into();
+        value["display"] = self.display.as_str().into();
+        value["all"] = self.all.clone().into();
+
+        value
+    }
+}
+
+/// Weapon script names mapped to the class, slot and items they affect, kept in file order.
+#[derive(Clone, Debug, Default)]
+pub struct Associations {
+    entries: Vec<(String, Association)>,
+}
+
+impl Associations {
+    pub fn builtin() -> Result<Self> {
+        Self::parse(BUILTIN).context("Built-in associations are invalid")
+    }
+
+    /// Built-in associations with the user's `associations.json` next to the binary merged over
+    /// them, so new weapons can be added without a rebuild.
+    pub fn load() -> Result<Self> {
+        let mut associations = Self::builtin()?;
+
+        let user_path = Self::user_path()?;
+        if user_path.exists() {
+            associations.merge(Self::read(&user_path)?);
+        }
+
+        Ok(associations)
+    }
+
+    pub fn user_path() -> Result<PathBuf> {
+        Ok(crate::exe_dir()?.join(USER_FILE))
+    }
+
+    /// Adds or replaces `key` in the user's `associations.json`, creating it if needed.
+    pub fn save_user_entry(key: &str, association: Association) -> Result<()> {
+        let user_path = Self::user_path()?;
+
+        let mut user = if user_path.exists() {
+            Self::read(&user_path)?
+        } else {
+            Self::default()
+        };
+
+        user.insert(key.to_string(), association);
+        user.write(&user_path)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        let s =
+            fs::read_to_string(path).with_context(|| format!("Failed to open {}", file_name))?;
+
+        Self::parse(&s).with_context(|| format!("Failed to parse {}", file_name))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        let json = json::parse(s)?;
+
+        if !json.is_object() {
+            bail!("Expected an object of weapon scripts");
+        }
+
+        let entries = json
+            .entries()
+            .map(|(key, value)| {
+                Association::from_json(value)
+                    .map(|a| (key.to_string(), a))
+                    .with_context(|| format!("Invalid entry `{}`", key))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { entries })
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = json::JsonValue::new_object();
+
+        for (key, association) in &self.entries {
+            json[key.as_str()] = association.to_json();
+        }
+
+        json::stringify_pretty(json, 4)
+    }
+
+    /// Replaces entries that already exist and appends new ones.
+    pub fn merge(&mut self, other: Self) {
+        for (key, association) in other.entries {
+            self.insert(key, association);
+        }
+    }
+
+    pub fn insert(&mut self, key: String, association: Association) {
+        match self.entries.iter_mut().find(|e| e.0 == key) {
+            Some(e) => e.1 = association,
+            None => self.entries.push((key, association)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Association> {
+        self.entries.iter().find(|e| e.0 == key).map(|e| &e.1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Association)> {
+        self.entries.iter().map(|e| (e.0.as_str(), &e.1))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Names of the `tf_weapon_*.txt` scripts in `scripts_dir` that have no associations.
+pub fn unknown_scripts(scripts_dir: &Path, associations: &Associations) -> Result<Vec<String>> {
+    let mut unknown = Vec::new();
+
+    for file in fs::read_dir(scripts_dir)
+        .with_context(|| format!("Failed to read folder `{}`", scripts_dir.display()))?
+    {
+        let path = file?.path();
+
+        if path.extension() != Some(std::ffi::OsStr::new("txt")) {
+            continue;
+        }
+
+        let name = path.file_stem().unwrap().to_string_lossy();
+
+        if name.starts_with("tf_weapon_") && associations.get(&name).is_none() {
+            unknown.push(name.into_owned());
+        }
+    }
+
+    unknown.sort();
+
+    Ok(unknown)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builtin() {
+        let a = Associations::builtin().unwrap();
+        let w = a.get("tf_weapon_scattergun").unwrap();
+
+        assert_eq!(w.class, Class::Scout);
+        assert_eq!(w.slot, Slot::Primary);
+        assert_eq!(w.display, "Scatterguns");
+        assert!(w.all.contains(&"Force-A-Nature".to_string()));
+    }
+
+    #[test]
+    fn invalid() {
+        let e = Associations::parse(
+            r#"{"tf_weapon_bat":{"class":"Scoot","slot":3,"display":"Bat","all":["Bat"]}}"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            format!("{:#}", e),
+            "Invalid entry `tf_weapon_bat`: Unknown class `Scoot`"
+        );
+
+        let e = Associations::parse(r#"{"tf_weapon_bat":{"class":"Scout","display":"Bat"}}"#)
+            .unwrap_err();
+
+        assert_eq!(
+            format!("{:#}", e),
+            "Invalid entry `tf_weapon_bat`: Missing or invalid `slot`"
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let mut a = Associations::builtin().unwrap();
+        let len = a.len();
+
+        let user = Associations::parse(
+            r#"{
+                "tf_weapon_bat":{"class":"Scout","slot":3,"display":"Bats","all":["Bat"]},
+                "tf_weapon_new":{"class":"All","slot":9,"display":"New","all":[]},
+                "tf_weapon_watch":{"class":"Spy","slot":5,"display":"Watches","all":[]}
+            }"#,
+        )
+        .unwrap();
+
+        a.merge(user);
+
+        assert_eq!(a.len(), len + 2);
+        assert_eq!(a.get("tf_weapon_bat").unwrap().display, "Bats");
+        assert_eq!(a.get("tf_weapon_new").unwrap().slot, Slot::Other);
+
+        let round_trip = Associations::parse(&a.to_json()).unwrap();
+
+        assert_eq!(round_trip.len(), a.len());
+        assert_eq!(round_trip.get("tf_weapon_new"), a.get("tf_weapon_new"));
+        // Both PDA slots keep their number.
+        assert_eq!(round_trip.get("tf_weapon_watch").unwrap().slot.to_u8(), 5);
+    }
+
+    #[test]
+    fn unknown_scripts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        for file in ["tf_weapon_bat.txt", "tf_weapon_new.txt", "readme.md"] {
+            fs::write(temp_dir.path().join(file), "").unwrap();
+        }
+
+        let unknown =
+            super::unknown_scripts(temp_dir.path(), &Associations::builtin().unwrap()).unwrap();
+
+        assert_eq!(unknown, vec!["tf_weapon_new".to_string()]);
+    }
+}
@@ -0,0 +1,284 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// Name of the sidecar kept next to the crosshairs, so the details travel with them.
+pub const FILE: &str = "crosshairs.json";
+
+/// What's known about a crosshair besides its texture.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrosshairMeta {
+    pub display: String,
+    pub author: String,
+    /// Pack or game the crosshair came from, like `Quake Live` or `CPMA`.
+    pub source: String,
+    pub tags: Vec<String>,
+    pub favourite: bool,
+}
+
+impl CrosshairMeta {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether `search`, in lowercase, is in any of the text fields.
+    pub fn contains(&self, search: &str) -> bool {
+        [&self.display, &self.author, &self.source]
+            .into_iter()
+            .chain(&self.tags)
+            .any(|s| s.to_lowercase().contains(search))
+    }
+
+    /// Whether `label` is the source or one of the tags, ignoring case.
+    pub fn has_label(&self, label: &str) -> bool {
+        self.source.eq_ignore_ascii_case(label)
+            || self.tags.iter().any(|t| t.eq_ignore_ascii_case(label))
+    }
+
+    fn from_json(value: &json::JsonValue) -> Result<Self> {
+        if !value.is_object() {
+            bail!("Expected an object");
+        }
+
+        let string = |key: &str| -> Result<String> {
+            match &value[key] {
+                json::JsonValue::Null => Ok(String::new()),
+                v => v
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("`{}` isn't a string", key)),
+            }
+        };
+
+        let tags = value["tags"]
+            .members()
+            .enumerate()
+            .map(|(i, t)| {
+                t.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("`tags[{}]` isn't a string", i))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let favourite = match &value["favourite"] {
+            json::JsonValue::Null => false,
+            v => v
+                .as_bool()
+                .ok_or_else(|| anyhow!("`favourite` isn't a boolean"))?,
+        };
+
+        Ok(Self {
+            display: string("display")?,
+            author: string("author")?,
+            source: string("source")?,
+            tags,
+            favourite,
+        })
+    }
+
+    /// Only the fields that are set, to keep the sidecar short.
+    fn to_json(&self) -> json::JsonValue {
+        let mut value = json::JsonValue::new_object();
+
+        for (key, s) in [
+            ("display", &self.display),
+            ("author", &self.author),
+            ("source", &self.source),
+        ] {
+            if !s.is_empty() {
+                value[key] = s.as_str().into();
+            }
+        }
+
+        if !self.tags.is_empty() {
+            value["tags"] = self.tags.clone().into();
+        }
+
+        if self.favourite {
+            value["favourite"] = true.into();
+        }
+
+        value
+    }
+}
+
+/// Details of the crosshairs in a folder, keyed on file name without the extension and kept in
+/// file order.
+#[derive(Clone, Debug, Default)]
+pub struct Catalogue {
+    entries: Vec<(String, CrosshairMeta)>,
+}
+
+impl Catalogue {
+    pub fn path(crosshair_dir: &Path) -> PathBuf {
+        crosshair_dir.join(FILE)
+    }
+
+    /// The catalogue of `crosshair_dir`, empty if it has none yet.
+    pub fn load(crosshair_dir: &Path) -> Result<Self> {
+        let path = Self::path(crosshair_dir);
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::read(&path)
+    }
+
+    pub fn save(&self, crosshair_dir: &Path) -> Result<()> {
+        self.write(&Self::path(crosshair_dir))
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        let s =
+            fs::read_to_string(path).with_context(|| format!("Failed to open {}", file_name))?;
+
+        Self::parse(&s).with_context(|| format!("Failed to parse {}", file_name))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        let json = json::parse(s)?;
+
+        if !json.is_object() {
+            bail!("Expected an object of crosshairs");
+        }
+
+        let entries = json
+            .entries()
+            .map(|(key, value)| {
+                CrosshairMeta::from_json(value)
+                    .map(|m| (key.to_string(), m))
+                    .with_context(|| format!("Invalid entry `{}`", key))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { entries })
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = json::JsonValue::new_object();
+
+        for (key, meta) in &self.entries {
+            json[key.as_str()] = meta.to_json();
+        }
+
+        json::stringify_pretty(json, 4)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&CrosshairMeta> {
+        self.entries.iter().find(|e| e.0 == key).map(|e| &e.1)
+    }
+
+    /// Adds or replaces the details of `key`, dropping it once nothing is left to remember.
+    pub fn set(&mut self, key: &str, meta: CrosshairMeta) {
+        let i = self.entries.iter().position(|e| e.0 == key);
+
+        match (i, meta.is_empty()) {
+            (Some(i), true) => {
+                self.entries.remove(i);
+            }
+            (Some(i), false) => self.entries[i].1 = meta,
+            (None, true) => {}
+            (None, false) => self.entries.push((key.to_string(), meta)),
+        }
+    }
+
+    /// Every source and tag in use, sorted and without duplicates, to filter by.
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = Vec::new();
+
+        for (_, meta) in &self.entries {
+            for label in std::iter::once(&meta.source).chain(&meta.tags) {
+                if !label.is_empty() && !labels.iter().any(|l| l.eq_ignore_ascii_case(label)) {
+                    labels.push(label.clone());
+                }
+            }
+        }
+
+        labels.sort_by_key(|l| l.to_lowercase());
+        labels
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let catalogue = Catalogue::parse(
+            r#"{
+                "ql_2": {"display": "QL dot", "source": "Quake Live", "tags": ["dot"]},
+                "cpma_12": {"author": "anon", "source": "CPMA", "favourite": true}
+            }"#,
+        )
+        .unwrap();
+
+        let ql = catalogue.get("ql_2").unwrap();
+        assert_eq!(ql.display, "QL dot");
+        assert!(!ql.favourite);
+        assert!(ql.contains("quake"));
+        assert!(ql.has_label("DOT"));
+
+        assert!(catalogue.get("cpma_12").unwrap().favourite);
+        assert_eq!(catalogue.labels(), ["CPMA", "dot", "Quake Live"]);
+
+        let round_trip = Catalogue::parse(&catalogue.to_json()).unwrap();
+        assert_eq!(round_trip.get("cpma_12"), catalogue.get("cpma_12"));
+
+        let e = Catalogue::parse(r#"{"dot": {"favourite": "yes"}}"#).unwrap_err();
+        assert_eq!(
+            format!("{:#}", e),
+            "Invalid entry `dot`: `favourite` isn't a boolean"
+        );
+    }
+
+    #[test]
+    fn bundled() {
+        let catalogue =
+            Catalogue::load(Path::new("resources/materials/vgui/replay/thumbnails")).unwrap();
+
+        assert_eq!(catalogue.get("ql_12").unwrap().source, "Quake Live");
+        assert_eq!(catalogue.labels(), ["CPMA", "Quake Live"]);
+    }
+
+    #[test]
+    fn set() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let mut catalogue = Catalogue::load(temp_dir.path()).unwrap();
+        assert!(catalogue.is_empty());
+
+        catalogue.set(
+            "dot",
+            CrosshairMeta {
+                favourite: true,
+                ..Default::default()
+            },
+        );
+        catalogue.set("circle", CrosshairMeta::default());
+        catalogue.save(temp_dir.path()).unwrap();
+
+        let mut catalogue = Catalogue::load(temp_dir.path()).unwrap();
+        assert_eq!(catalogue.len(), 1);
+
+        catalogue.set("dot", CrosshairMeta::default());
+        assert!(catalogue.is_empty());
+    }
+}
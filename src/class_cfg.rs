@@ -0,0 +1,408 @@
+use crate::associations::{Class, Slot};
+use crate::model::{self, Weapon};
+use crate::simulate::Settings;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// First line of every generated config, to tell them from the user's own.
+const HEADER: &str = "// Generated by crosshair-switcher";
+/// Config setting the weapon's own crosshair, as quotes can't go in an alias.
+const STOCK: &str = "crosshair_switcher_stock";
+/// Slots with a key of their own, in the order the mouse wheel goes through them.
+const SLOTS: [Slot; 5] = [
+    Slot::Primary,
+    Slot::Secondary,
+    Slot::Melee,
+    Slot::Pda,
+    Slot::Pda2,
+];
+
+/// How the generated configs draw crosshairs and the keys they bind.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// Scale and colour, the scale being the one for crosshairs drawn at the size of their
+    /// weapon script.
+    pub settings: Settings,
+    pub lastinv: String,
+    pub mouse_wheel: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            settings: Settings::default(),
+            lastinv: "q".to_string(),
+            mouse_wheel: true,
+        }
+    }
+}
+
+/// The crosshair of a slot, as set with `cl_crosshair_file` and `cl_crosshair_scale`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlotCrosshair {
+    pub slot: Slot,
+    /// Relative to `materials/vgui/crosshairs`, or empty for the weapon's own crosshair.
+    pub file: String,
+    pub scale: f32,
+}
+
+/// `material` as `cl_crosshair_file` takes it. The stock sprite sheet can't be, since it needs
+/// the part the weapon script picks, so it's left to the weapon.
+fn crosshair_file(material: &str) -> String {
+    let key = model::material_key(material);
+
+    match key.strip_prefix("vgui/") {
+        Some(file) if !key.starts_with("vgui/crosshairs/") => format!("../{}", file),
+        Some(_) => key["vgui/crosshairs/".len()..].to_string(),
+        None => String::new(),
+    }
+}
+
+/// The crosshair most weapons of each class use in each slot, all-class weapons counting only
+/// for slots the class has none of its own in.
+pub fn loadout(weapons: &[Weapon], settings: &Settings) -> Vec<(Class, Vec<SlotCrosshair>)> {
+    let mut loadout = Vec::new();
+
+    for class in Class::ALL.into_iter().filter(|c| *c != Class::All) {
+        let mut slots = Vec::new();
+
+        for slot in SLOTS {
+            let in_slot = |class: Class| {
+                weapons
+                    .iter()
+                    .filter(|w| {
+                        w.association
+                            .as_ref()
+                            .is_some_and(|a| a.class == class && a.slot == slot)
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            let mut candidates = in_slot(class);
+            if candidates.is_empty() {
+                candidates = in_slot(Class::All);
+            }
+
+            let mut counts = HashMap::new();
+            for w in &candidates {
+                *counts
+                    .entry(model::material_key(&w.file.crosshair))
+                    .or_insert(0) += 1;
+            }
+
+            // The first weapon wins ties, so the same scripts always give the same configs.
+            let chosen = candidates
+                .iter()
+                .fold(None::<&&Weapon>, |best, w| match best {
+                    Some(b)
+                        if counts[&model::material_key(&b.file.crosshair)]
+                            >= counts[&model::material_key(&w.file.crosshair)] =>
+                    {
+                        Some(b)
+                    }
+                    _ => Some(w),
+                });
+
+            if let Some(w) = chosen {
+                slots.push(SlotCrosshair {
+                    slot,
+                    file: crosshair_file(&w.file.crosshair),
+                    scale: w.file.crosshair_rect.width as f32 * settings.scale / 32.0,
+                });
+            }
+        }
+
+        if !slots.is_empty() {
+            loadout.push((class, slots));
+        }
+    }
+
+    loadout
+}
+
+/// A class config switching crosshairs with the slot keys, `lastinv` and the mouse wheel.
+pub fn render(class: Class, slots: &[SlotCrosshair], options: &Options) -> String {
+    let [r, g, b] = options.settings.color;
+    let mut lines = vec![
+        HEADER.to_string(),
+        format!(
+            "// Per-slot crosshairs of the {} for sv_pure servers.",
+            class
+        ),
+        String::new(),
+    ];
+
+    for (i, s) in slots.iter().enumerate() {
+        let n = s.slot.to_u8();
+        let next = slots[(i + 1) % slots.len()].slot.to_u8();
+        let prev = slots[(i + slots.len() - 1) % slots.len()].slot.to_u8();
+
+        let file = if s.file.is_empty() {
+            format!("exec {}", STOCK)
+        } else {
+            format!("cl_crosshair_file {}", s.file)
+        };
+
+        lines.push(format!(
+            "alias cs_{n} \"{}; cl_crosshair_scale {}; cl_crosshair_red {r}; \
+             cl_crosshair_green {g}; cl_crosshair_blue {b}\"",
+            file,
+            s.scale.round(),
+        ));
+        lines.push(format!("alias cs_save_{n} \"alias cs_last cs_select_{n}\""));
+        lines.push(format!(
+            "alias cs_select_{n} \"cs_save; alias cs_save cs_save_{n}; \
+             alias cs_next cs_select_{next}; alias cs_prev cs_select_{prev}; cs_{n}\""
+        ));
+        lines.push(format!("bind {n} \"slot{n}; cs_select_{n}\""));
+    }
+
+    lines.push(String::new());
+    lines.push(format!("bind {} \"lastinv; cs_last\"", options.lastinv));
+
+    if options.mouse_wheel {
+        lines.push("bind MWHEELUP \"invprev; cs_prev\"".to_string());
+        lines.push("bind MWHEELDOWN \"invnext; cs_next\"".to_string());
+    }
+
+    // Spawning with the first slot out.
+    let first = slots[0].slot.to_u8();
+    lines.push(format!("alias cs_save cs_save_{first}"));
+    lines.push(format!("cs_select_{first}"));
+    lines.push(String::new());
+
+    lines.join("\n")
+}
+
+/// The configs [`write`] wrote and the ones it left alone.
+#[derive(Debug)]
+pub struct Written {
+    /// Each file written and whether it's new.
+    pub files: Vec<(PathBuf, bool)>,
+    /// Files already there that aren't ours.
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Whether the file at `path` is missing or one we generated, and so ours to write.
+fn ours(path: &Path) -> bool {
+    fs::read_to_string(path).map_or(true, |s| s.starts_with(HEADER))
+}
+
+/// Where the config of `class` goes in `dir`. A config of the user's own is left alone, with
+/// ours written next to it for theirs to `exec`.
+pub fn path(dir: &Path, class: Class) -> PathBuf {
+    let name = class.to_str().to_ascii_lowercase();
+    let path = dir.join(&name).with_extension("cfg");
+
+    if ours(&path) {
+        path
+    } else {
+        dir.join(format!("{}_crosshairs.cfg", name))
+    }
+}
+
+/// Writes the configs of every class into `dir`, along with the one they share, returning the
+/// files written and skipping any already there that aren't ours.
+pub fn write(weapons: &[Weapon], dir: &Path, options: &Options) -> Result<Written> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create folder `{}`", dir.display()))?;
+
+    let mut files = vec![(
+        dir.join(STOCK).with_extension("cfg"),
+        format!("{}\ncl_crosshair_file \"\"\n", HEADER),
+    )];
+
+    for (class, slots) in loadout(weapons, &options.settings) {
+        files.push((path(dir, class), render(class, &slots, options)));
+    }
+
+    let mut written = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (path, s) in files {
+        if !ours(&path) {
+            skipped.push(path);
+            continue;
+        }
+
+        let created = !path.exists();
+
+        fs::write(&path, s).with_context(|| format!("Failed to write {}", path.display()))?;
+
+        written.push((path, created));
+    }
+
+    Ok(Written {
+        files: written,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::associations::Associations;
+
+    fn weapons() -> Vec<Weapon> {
+        let (mut weapons, _) = model::load_weapons(
+            Path::new("resources/scripts"),
+            &Associations::builtin().unwrap(),
+        )
+        .unwrap();
+
+        for w in &mut weapons {
+            if w.file.name == "tf_weapon_rocketlauncher" {
+                w.file.crosshair = "vgui/replay/thumbnails/dot".into();
+                w.file.crosshair_rect.width = 64;
+            }
+        }
+
+        weapons
+    }
+
+    #[test]
+    fn crosshair_files() {
+        assert_eq!(
+            crosshair_file("vgui/replay/thumbnails/Dot"),
+            "../replay/thumbnails/dot"
+        );
+        assert_eq!(crosshair_file("vgui/crosshairs/crosshair1"), "crosshair1");
+        assert_eq!(crosshair_file("sprites/crosshairs"), "");
+    }
+
+    #[test]
+    fn loadout() {
+        let weapons = weapons();
+        let loadout = super::loadout(&weapons, &Settings::default());
+
+        let soldier = &loadout
+            .iter()
+            .find(|(c, _)| *c == Class::Soldier)
+            .unwrap()
+            .1;
+        assert_eq!(
+            soldier.iter().map(|s| s.slot).collect::<Vec<_>>(),
+            [Slot::Primary, Slot::Secondary, Slot::Melee]
+        );
+
+        // The stock rocket launcher is one of several primaries, most still stock.
+        assert_eq!(soldier[0].file, "");
+
+        let only_dot = weapons
+            .into_iter()
+            .filter(|w| {
+                !w.association
+                    .as_ref()
+                    .is_some_and(|a| a.class == Class::Soldier && a.slot == Slot::Primary)
+                    || w.file.name == "tf_weapon_rocketlauncher"
+            })
+            .collect::<Vec<_>>();
+        let loadout = super::loadout(&only_dot, &Settings::default());
+        let soldier = &loadout
+            .iter()
+            .find(|(c, _)| *c == Class::Soldier)
+            .unwrap()
+            .1;
+
+        assert_eq!(
+            soldier[0],
+            SlotCrosshair {
+                slot: Slot::Primary,
+                file: "../replay/thumbnails/dot".into(),
+                scale: 64.0,
+            }
+        );
+    }
+
+    #[test]
+    fn render() {
+        let slots = [
+            SlotCrosshair {
+                slot: Slot::Primary,
+                file: "../replay/thumbnails/dot".into(),
+                scale: 64.0,
+            },
+            SlotCrosshair {
+                slot: Slot::Melee,
+                file: String::new(),
+                scale: 32.0,
+            },
+        ];
+
+        let cfg = super::render(Class::Soldier, &slots, &Options::default());
+
+        assert!(cfg.starts_with(HEADER));
+        assert!(cfg.contains(
+            "alias cs_1 \"cl_crosshair_file ../replay/thumbnails/dot; cl_crosshair_scale 64; \
+             cl_crosshair_red 200; cl_crosshair_green 200; cl_crosshair_blue 200\""
+        ));
+        assert!(cfg.contains("alias cs_3 \"exec crosshair_switcher_stock; cl_crosshair_scale 32"));
+        // The wheel wraps around the slots the class has.
+        assert!(cfg.contains(
+            "alias cs_select_3 \"cs_save; alias cs_save cs_save_3; alias cs_next cs_select_1; \
+             alias cs_prev cs_select_1; cs_3\""
+        ));
+        assert!(cfg.contains("bind q \"lastinv; cs_last\""));
+        assert!(cfg.ends_with("cs_select_1\n"));
+    }
+
+    #[test]
+    fn keeps_user_configs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+
+        fs::write(dir.join("scout.cfg"), "viewmodel_fov 90").unwrap();
+        fs::write(dir.join("soldier.cfg"), format!("{}\n", HEADER)).unwrap();
+
+        let Written {
+            files: written,
+            skipped,
+        } = write(&weapons(), dir, &Options::default()).unwrap();
+
+        assert_eq!(written.len(), 10);
+        assert!(skipped.is_empty());
+        assert!(written.contains(&(dir.join("scout_crosshairs.cfg"), true)));
+        assert!(written.contains(&(dir.join("soldier.cfg"), false)));
+        assert_eq!(
+            fs::read_to_string(dir.join("scout.cfg")).unwrap(),
+            "viewmodel_fov 90"
+        );
+    }
+
+    #[test]
+    fn keeps_foreign_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+
+        // Theirs beside theirs, so there's nowhere left for ours.
+        fs::write(dir.join("scout.cfg"), "viewmodel_fov 90").unwrap();
+        fs::write(dir.join("scout_crosshairs.cfg"), "cl_crosshair_file dot").unwrap();
+        fs::write(dir.join("crosshair_switcher_stock.cfg"), "echo hi").unwrap();
+
+        let Written {
+            files: written,
+            skipped,
+        } = write(&weapons(), dir, &Options::default()).unwrap();
+
+        assert_eq!(written.len(), 8);
+        assert_eq!(
+            skipped,
+            [
+                dir.join("crosshair_switcher_stock.cfg"),
+                dir.join("scout_crosshairs.cfg")
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("scout_crosshairs.cfg")).unwrap(),
+            "cl_crosshair_file dot"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("crosshair_switcher_stock.cfg")).unwrap(),
+            "echo hi"
+        );
+    }
+}
@@ -0,0 +1,717 @@
+use crate::associations::{self, Association, Associations, Class, Slot};
+use crate::class_cfg;
+use crate::contact_sheet::{self, Options};
+use crate::groups::Groups;
+use crate::items_game;
+use crate::loadout_card;
+use crate::localization::Localization;
+use crate::manifest::{self, Change, Manifest};
+use crate::model;
+use crate::overrides;
+use crate::preview::Background;
+use crate::prune;
+use crate::rebase::{self, Rebased};
+use crate::rules::{self, Assignment, Loadout, Rule};
+use crate::ExplosionEffect;
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+pub fn run(args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("unknown") => unknown()?,
+        Some("associate") => associate(&args[1..])?,
+        Some("contact-sheet") => export_contact_sheet(&args[1..])?,
+        Some("loadout-card") => export_loadout_card(&args[1..])?,
+        Some("prune") => prune(&args[1..])?,
+        Some("restore") => restore()?,
+        Some("manifest") => write_manifest(&args[1..])?,
+        Some("status") => status()?,
+        Some("uninstall") => uninstall()?,
+        Some("rebase") => rebase(&args[1..])?,
+        Some("overrides") => list_overrides(&args[1..])?,
+        Some("class-cfg") => write_class_cfgs(&args[1..])?,
+        Some("associations") => generate_associations(&args[1..])?,
+        Some("rules") => loadout_rules(&args[1..])?,
+        Some("groups") => weapon_groups(&args[1..])?,
+        Some("help" | "--help" | "-h") | None => print_help(),
+        Some(task) => {
+            print_help();
+            bail!("Unknown task `{}`", task);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_help() {
+    eprintln!(
+        "\
+USAGE:
+    crosshair-switcher [TASK]
+
+    Launches the GUI when no task is given.
+
+TASKS:
+    unknown         Lists weapon scripts in `scripts` that have no associations
+    associate <script> <class> <slot> <display> [items...]
+                    Adds a weapon script to the `associations.json` next to the binary
+    contact-sheet <output.png> [columns] [cell size] [background]
+                    Renders every crosshair into a labelled grid, on a checkerboard,
+                    black, white or sky blue background
+    loadout-card <output.png>
+                    Renders the crosshair and explosion of every weapon, by class and slot
+    prune [--dry-run]
+                    Moves crosshairs no weapon script uses into the `pruned` folder, or only
+                    lists them with `--dry-run`
+    restore         Moves pruned crosshairs back
+    manifest [source]
+                    Records every file in `scripts` and `materials` as installed by the tool
+    status          Lists installed files edited or removed since the tool last wrote them
+    uninstall       Removes the files the tool installed, keeping ones edited since
+    rebase <baseline> <new scripts> [--dry-run]
+                    Re-applies crosshair and explosion edits made to the `baseline` stock
+                    scripts onto updated ones, listing conflicts to check
+    overrides [--merge]
+                    Lists weapon scripts other folders and VPKs in the custom folder also
+                    have, and which one the game loads. `--merge` copies our crosshairs and
+                    explosions into the winning copies in other folders
+    class-cfg [folder]
+                    Writes class configs switching crosshairs by slot with `cl_crosshair_file`,
+                    for sv_pure servers, into `cfg` next to the binary or `folder`. Configs of
+                    your own are kept, with ours written next to them as `<class>_crosshairs`
+    associations <items_game.txt> [output] [scripts folder]
+                    Regenerates associations from the game's item schema into `output`, or
+                    the `associations.json` next to the binary, listing added and removed
+                    items. Only weapon scripts in `scripts folder` or `scripts` are kept
+    rules [add <when> <crosshair|-> [explosion] | remove <n> | apply [--dry-run]]
+                    Lists, adds, removes or applies the rules of the `loadout.json` next to
+                    the binary, giving every weapon matching `when` a crosshair and
+                    explosion, like `rules add \"MeleeWeapon = 1\" none` or
+                    `rules add \"slot = 2 and class in [Scout, Engineer]\" dot`. Later rules
+                    win over earlier ones
+    groups [add <name> <scripts...> | remove <name> | apply <name> <crosshair|-> [explosion]]
+                    Lists, adds, removes or applies a crosshair and explosion to named groups
+                    of weapon scripts, kept in the `groups.json` next to the binary
+"
+    )
+}
+
+fn unknown() -> Result<()> {
+    let associations = Associations::load()?;
+
+    for name in associations::unknown_scripts(&crate::scripts_dir()?, &associations)? {
+        println!("{}", name);
+    }
+
+    Ok(())
+}
+
+fn associate(args: &[String]) -> Result<()> {
+    if args.len() < 4 {
+        bail!("Expected `associate <script> <class> <slot> <display> [items...]`");
+    }
+
+    let key = args[0].trim_end_matches(".txt");
+
+    if !crate::scripts_dir()?
+        .join(key)
+        .with_extension("txt")
+        .exists()
+    {
+        bail!("{}.txt doesn't exist in `scripts` folder", key);
+    }
+
+    let association = Association {
+        class: parse_class(&args[1])?,
+        slot: parse_slot(&args[2])?,
+        display: args[3].clone(),
+        all: args[4..].to_vec(),
+    };
+
+    Associations::save_user_entry(key, association)?;
+
+    println!("Added {} to {}", key, Associations::user_path()?.display());
+
+    Ok(())
+}
+
+fn export_contact_sheet(args: &[String]) -> Result<()> {
+    let output = match args.first() {
+        Some(o) => Path::new(o),
+        None => bail!("Expected `contact-sheet <output.png> [columns] [cell size] [background]`"),
+    };
+
+    let mut options = Options::default();
+
+    if let Some(columns) = args.get(1) {
+        options.columns =
+            columns.parse().ok().filter(|&n| n >= 1).ok_or_else(|| {
+                anyhow!("Invalid column count `{}`, expected at least 1", columns)
+            })?;
+    }
+
+    if let Some(cell_size) = args.get(2) {
+        options.cell_size = cell_size
+            .parse()
+            .ok()
+            .filter(|n| (1..=contact_sheet::MAX_CELL_SIZE).contains(n))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Invalid cell size `{}`, expected 1 to {}",
+                    cell_size,
+                    contact_sheet::MAX_CELL_SIZE
+                )
+            })?;
+    }
+
+    if let Some(background) = args.get(3) {
+        options.background = parse_background(background)?;
+    }
+
+    for e in contact_sheet::export(&crate::crosshair_dir()?, output, &options)? {
+        eprintln!("{}", e);
+    }
+
+    println!("Exported {}", output.display());
+
+    Ok(())
+}
+
+fn export_loadout_card(args: &[String]) -> Result<()> {
+    let output = match args.first() {
+        Some(o) => Path::new(o),
+        None => bail!("Expected `loadout-card <output.png>`"),
+    };
+
+    let (weapons, errors) = model::load_weapons(&crate::scripts_dir()?, &Associations::load()?)?;
+
+    for e in errors {
+        eprintln!("{}", e);
+    }
+
+    loadout_card::export(&weapons, &crate::materials_dir()?, output)?;
+
+    println!("Exported {}", output.display());
+
+    Ok(())
+}
+
+fn prune(args: &[String]) -> Result<()> {
+    let dry_run = match args.first().map(String::as_str) {
+        Some("--dry-run") => true,
+        None => false,
+        Some(_) => bail!("Expected `prune [--dry-run]`"),
+    };
+
+    let (weapons, errors) = model::load_weapons(&crate::scripts_dir()?, &Associations::load()?)?;
+
+    let root = crate::exe_dir()?;
+    let plan = prune::plan_weapons(&root, &weapons, &errors)?;
+
+    for file in &plan.prune {
+        println!("{}", file.display());
+    }
+
+    if dry_run {
+        println!(
+            "Would prune {} file(s), keeping {}",
+            plan.prune.len(),
+            plan.keep.len()
+        );
+
+        return Ok(());
+    }
+
+    let errors = prune::prune(&root, &plan)?;
+
+    for e in &errors {
+        eprintln!("{}", e);
+    }
+
+    println!(
+        "Pruned {} file(s) into `{}`, keeping {}",
+        plan.prune.len() - errors.len(),
+        prune::PRUNED_DIR,
+        plan.keep.len()
+    );
+
+    Ok(())
+}
+
+fn restore() -> Result<()> {
+    let (restored, errors) = prune::restore(&crate::exe_dir()?)?;
+
+    for e in errors {
+        eprintln!("{}", e);
+    }
+
+    println!("Restored {} file(s)", restored.len());
+
+    Ok(())
+}
+
+fn write_manifest(args: &[String]) -> Result<()> {
+    let root = crate::exe_dir()?;
+    let source = args.first().map_or("manual", String::as_str);
+
+    let mut manifest = Manifest::scan(&root, source)?;
+    manifest.save(&root)?;
+
+    println!("Recorded {} file(s) in {}", manifest.len(), manifest::FILE);
+
+    Ok(())
+}
+
+fn status() -> Result<()> {
+    let root = crate::exe_dir()?;
+    let manifest = Manifest::load(&root)?;
+
+    if manifest.is_empty() {
+        bail!("No {} next to the binary", manifest::FILE);
+    }
+
+    for change in manifest.changes(&root) {
+        match change {
+            Change::Modified(file) => println!("modified  {}", file),
+            Change::Missing(file) => println!("missing   {}", file),
+        }
+    }
+
+    Ok(())
+}
+
+fn uninstall() -> Result<()> {
+    let root = crate::exe_dir()?;
+    let mut manifest = Manifest::load(&root)?;
+
+    if manifest.is_empty() {
+        bail!("No {} next to the binary", manifest::FILE);
+    }
+
+    let (removed, errors) = manifest.uninstall(&root)?;
+
+    for e in errors {
+        eprintln!("{}", e);
+    }
+
+    println!("Removed {} file(s)", removed.len());
+
+    Ok(())
+}
+
+fn rebase(args: &[String]) -> Result<()> {
+    let (base, new, dry_run) = match args {
+        [base, new] => (base, new, false),
+        [base, new, flag] if flag == "--dry-run" => (base, new, true),
+        _ => bail!("Expected `rebase <baseline> <new scripts> [--dry-run]`"),
+    };
+
+    let root = crate::exe_dir()?;
+    let scripts = crate::scripts_dir()?;
+    let report = rebase::rebase(Path::new(base), &scripts, Path::new(new), dry_run)?;
+
+    let (mut edited, mut conflicts, mut stock, mut added) = (0, 0, 0, 0);
+
+    for (name, rebased) in &report {
+        match rebased {
+            Rebased::Stock => stock += 1,
+            Rebased::Edited(fields) => {
+                edited += 1;
+                println!("rebased   {} ({})", name, fields.join(", "));
+            }
+            Rebased::Conflict(fields, reason) => {
+                conflicts += 1;
+                println!("conflict  {} ({}); {}", name, fields.join(", "), reason);
+            }
+            Rebased::Added => {
+                added += 1;
+                println!("added     {}", name);
+            }
+            Rebased::Removed => println!("removed   {}; kept as is", name),
+        }
+    }
+
+    println!(
+        "{} {} script(s) with edits, {} with conflicts to check, {} unedited updated to stock \
+         and {} added",
+        if dry_run { "Would rebase" } else { "Rebased" },
+        edited,
+        conflicts,
+        stock,
+        added
+    );
+
+    if dry_run {
+        return Ok(());
+    }
+
+    let mut manifest = Manifest::load(&root)?;
+
+    for (name, rebased) in &report {
+        if *rebased != Rebased::Removed {
+            manifest.record(&root, &scripts.join(name), *rebased == Rebased::Added)?;
+        }
+    }
+
+    manifest.save(&root)
+}
+
+fn list_overrides(args: &[String]) -> Result<()> {
+    let merge = match args.first().map(String::as_str) {
+        Some("--merge") => true,
+        None => false,
+        Some(_) => bail!("Expected `overrides [--merge]`"),
+    };
+
+    let ours = crate::exe_dir()?;
+    let (overrides, errors) = overrides::scan(&ours)?;
+
+    for e in errors {
+        eprintln!("{}", e);
+    }
+
+    for o in &overrides {
+        let winner = o.winner();
+
+        if winner == ours {
+            println!("{}: ours wins", o.script);
+            continue;
+        }
+
+        println!(
+            "{}: {} wins",
+            o.script,
+            winner.file_name().unwrap().to_string_lossy()
+        );
+
+        if !merge {
+            continue;
+        }
+
+        match overrides::merge(&ours, o) {
+            Ok(missing) if missing.is_empty() => println!("    merged"),
+            Ok(missing) => println!("    merged, without {}", missing.join(", ")),
+            Err(e) => eprintln!("Skipping {}; {:#}", o.script, e),
+        }
+    }
+
+    Ok(())
+}
+
+fn write_class_cfgs(args: &[String]) -> Result<()> {
+    let root = crate::exe_dir()?;
+    let dir = match args.first() {
+        Some(dir) => Path::new(dir).to_path_buf(),
+        None => root.join("cfg"),
+    };
+
+    let (weapons, errors) = model::load_weapons(&crate::scripts_dir()?, &Associations::load()?)?;
+
+    for e in errors {
+        eprintln!("{}", e);
+    }
+
+    let class_cfg::Written {
+        files: written,
+        skipped,
+    } = class_cfg::write(&weapons, &dir, &class_cfg::Options::default())?;
+
+    for path in &skipped {
+        eprintln!("Skipped {}, which isn't one of ours", path.display());
+    }
+
+    for (path, created) in &written {
+        if path.starts_with(&root) {
+            manifest::record_write(path, *created)?;
+        }
+
+        println!("{}", path.display());
+    }
+
+    println!(
+        "Wrote {} config(s), skipped {}",
+        written.len(),
+        skipped.len()
+    );
+
+    Ok(())
+}
+
+fn generate_associations(args: &[String]) -> Result<()> {
+    let items_game = match args.first() {
+        Some(i) => Path::new(i),
+        None => bail!("Expected `associations <items_game.txt> [output] [scripts folder]`"),
+    };
+
+    let output = match args.get(1) {
+        Some(o) => Path::new(o).to_path_buf(),
+        None => Associations::user_path()?,
+    };
+
+    let scripts_dir = match args.get(2) {
+        Some(s) => Path::new(s).to_path_buf(),
+        None => crate::scripts_dir()?,
+    };
+
+    // Names are in English, from `tf/resource` when the schema is in `tf/scripts/items`.
+    let english = items_game
+        .ancestors()
+        .nth(3)
+        .map(|tf| tf.join("resource/tf_english.txt"))
+        .filter(|p| p.exists());
+    let localization = match english {
+        Some(path) => Localization::open(&path)?,
+        None => Localization::load()?,
+    };
+
+    let s = fs::read_to_string(items_game)
+        .with_context(|| format!("Failed to open {}", items_game.display()))?;
+    let items = items_game::items(&s, &localization)
+        .with_context(|| format!("Failed to parse {}", items_game.display()))?;
+
+    let scripts = associations::unknown_scripts(&scripts_dir, &Associations::default())?;
+
+    let current = if output.exists() {
+        Associations::read(&output)?
+    } else {
+        Associations::load()?
+    };
+
+    let generated = items_game::generate(&items, Some(&scripts), &current);
+
+    for change in items_game::changes(&current, &generated) {
+        match change {
+            items_game::Change::Added(key, item) => println!("added    {}: {}", key, item),
+            items_game::Change::Removed(key, item) => println!("removed  {}: {}", key, item),
+            items_game::Change::Moved(key, class, slot) => {
+                println!("moved    {} to {} {}", key, class, slot)
+            }
+        }
+    }
+
+    generated.write(&output)?;
+
+    println!(
+        "Wrote {} weapon script(s) to {}",
+        generated.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+fn loadout_rules(args: &[String]) -> Result<()> {
+    let mut loadout = Loadout::load()?;
+
+    match args.first().map(String::as_str) {
+        None | Some("list") => {
+            for (i, rule) in loadout.rules.iter().enumerate() {
+                println!("{}. {}", i + 1, rule);
+            }
+        }
+        Some("add") if args.len() >= 3 => {
+            let crosshair = Some(args[2].clone()).filter(|c| c != "-");
+            let explosion = args.get(3).map(|e| ExplosionEffect::from(e.as_str()));
+
+            loadout.rules.push(Rule::new(&args[1], crosshair, explosion)?);
+            loadout.save()?;
+
+            println!("Added rule {}", loadout.rules.len());
+        }
+        Some("remove") if args.len() == 2 => {
+            let n = args[1]
+                .parse::<usize>()
+                .ok()
+                .filter(|n| (1..=loadout.rules.len()).contains(n))
+                .ok_or_else(|| anyhow!("No rule `{}`", args[1]))?;
+
+            let rule = loadout.rules.remove(n - 1);
+            loadout.save()?;
+
+            println!("Removed {}", rule);
+        }
+        Some("apply") => {
+            let dry_run = match args.get(1).map(String::as_str) {
+                Some("--dry-run") => true,
+                None => false,
+                Some(_) => bail!("Expected `rules apply [--dry-run]`"),
+            };
+
+            let (weapons, errors) =
+                model::load_weapons(&crate::scripts_dir()?, &Associations::load()?)?;
+
+            for e in errors {
+                eprintln!("{}", e);
+            }
+
+            let assignments = loadout.assignments(&weapons);
+
+            if dry_run {
+                for a in &assignments {
+                    let weapon = &weapons[a.weapon];
+
+                    if let Some(crosshair) = &a.crosshair {
+                        println!(
+                            "{}: {} -> {}",
+                            weapon.file.name,
+                            weapon.crosshair_file_name(),
+                            crosshair
+                        );
+                    }
+
+                    if let Some(explosion) = &a.explosion {
+                        println!("{}: explosion -> {}", weapon.file.name, explosion.to_str());
+                    }
+                }
+
+                println!("Would change {} weapon script(s)", assignments.len());
+
+                return Ok(());
+            }
+
+            let (changes, errors) = rules::apply(&weapons, &assignments, &crate::crosshair_dir()?);
+
+            for c in &changes {
+                println!("{}", c);
+            }
+
+            for e in &errors {
+                eprintln!("{}", e);
+            }
+
+            println!(
+                "Changed {} weapon script(s)",
+                assignments.len() - errors.len()
+            );
+        }
+        Some(_) => bail!(
+            "Expected `rules [add <when> <crosshair|-> [explosion] | remove <n> | apply [--dry-run]]`"
+        ),
+    }
+
+    Ok(())
+}
+
+fn weapon_groups(args: &[String]) -> Result<()> {
+    let mut groups = Groups::load()?;
+
+    match args.first().map(String::as_str) {
+        None | Some("list") => {
+            for (name, scripts) in groups.iter() {
+                println!("{}: {}", name, scripts.join(", "));
+            }
+        }
+        Some("add") if args.len() >= 3 => {
+            let scripts_dir = crate::scripts_dir()?;
+            let scripts = args[2..]
+                .iter()
+                .map(|s| s.trim_end_matches(".txt").to_string())
+                .collect::<Vec<_>>();
+
+            for script in &scripts {
+                if !scripts_dir.join(script).with_extension("txt").exists() {
+                    bail!("{}.txt doesn't exist in `scripts` folder", script);
+                }
+            }
+
+            groups.set(&args[1], scripts)?;
+            groups.save()?;
+
+            println!("Saved group `{}`", args[1].trim());
+        }
+        Some("remove") if args.len() == 2 => {
+            if !groups.remove(&args[1]) {
+                bail!("No group `{}`", args[1]);
+            }
+
+            groups.save()?;
+
+            println!("Removed group `{}`", args[1]);
+        }
+        Some("apply") if args.len() >= 3 => {
+            let scripts = groups
+                .get(&args[1])
+                .ok_or_else(|| anyhow!("No group `{}`", args[1]))?;
+
+            let crosshair = Some(args[2].clone())
+                .filter(|c| c != "-")
+                .map(|c| model::crosshair_stem(&c).to_string());
+            let explosion = args.get(3).map(|e| ExplosionEffect::from(e.as_str()));
+
+            if crosshair.is_none() && explosion.is_none() {
+                bail!("Expected a crosshair or explosion for group `{}`", args[1]);
+            }
+
+            let (weapons, errors) =
+                model::load_weapons(&crate::scripts_dir()?, &Associations::load()?)?;
+
+            for e in errors {
+                eprintln!("{}", e);
+            }
+
+            let assignments = weapons
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| scripts.contains(&w.file.name))
+                .map(|(i, w)| Assignment {
+                    weapon: i,
+                    crosshair: crosshair.clone(),
+                    explosion: explosion.clone().filter(|_| w.uses_explosion()),
+                })
+                .filter(|a| a.crosshair.is_some() || a.explosion.is_some())
+                .collect::<Vec<_>>();
+
+            let (changes, errors) = rules::apply(&weapons, &assignments, &crate::crosshair_dir()?);
+
+            for c in &changes {
+                println!("{}", c);
+            }
+
+            for e in &errors {
+                eprintln!("{}", e);
+            }
+
+            println!(
+                "Changed {} weapon script(s)",
+                assignments.len() - errors.len()
+            );
+        }
+        Some(_) => bail!(
+            "Expected `groups [add <name> <scripts...> | remove <name> | \
+             apply <name> <crosshair|-> [explosion]]`"
+        ),
+    }
+
+    Ok(())
+}
+
+fn parse_class(s: &str) -> Result<Class> {
+    Class::ALL
+        .into_iter()
+        .find(|c| c.to_str().eq_ignore_ascii_case(s))
+        .ok_or_else(|| anyhow!("Unknown class `{}`", s))
+}
+
+fn parse_slot(s: &str) -> Result<Slot> {
+    if let Ok(n) = s.parse::<u8>() {
+        return Slot::try_from(n);
+    }
+
+    Slot::ALL
+        .into_iter()
+        .find(|slot| slot.to_str().eq_ignore_ascii_case(s))
+        .ok_or_else(|| anyhow!("Unknown slot `{}`", s))
+}
+
+fn parse_background(s: &str) -> Result<Background> {
+    let name = s.replace(['-', '_'], " ");
+
+    Background::NAMES
+        .iter()
+        .position(|n| n.eq_ignore_ascii_case(&name))
+        .map(Background::from_index)
+        .ok_or_else(|| anyhow!("Unknown background `{}`", s))
+}
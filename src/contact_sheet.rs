@@ -0,0 +1,214 @@
+use crate::font;
+use crate::preview::{self, Background};
+use crate::texture;
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, FilterType, Rgba, RgbaImage};
+
+/// Space around crosshairs and labels, in pixels.
+const PADDING: u32 = 4;
+const LABEL_HEIGHT: u32 = font::GLYPH_HEIGHT + 2 * PADDING;
+const LABEL_BACKGROUND: Rgba<u8> = Rgba([48, 48, 48, 255]);
+const LABEL_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Largest cell size, to keep sheets of many crosshairs within memory.
+pub const MAX_CELL_SIZE: u32 = 1024;
+
+/// A crosshair's name and full size image.
+pub type NamedImage = (String, RgbaImage);
+
+#[derive(Clone)]
+pub struct Options {
+    /// Width and height of the area each crosshair is scaled to fit, keeping its aspect ratio.
+    pub cell_size: u32,
+    pub columns: u32,
+    pub background: Background,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            cell_size: 96,
+            columns: 10,
+            background: Background::Checkerboard,
+        }
+    }
+}
+
+/// Decodes every VTF in `dir`, sorted by name. Crosshairs that fail to decode are returned as
+/// errors instead.
+pub fn load(dir: &Path) -> Result<(Vec<NamedImage>, Vec<String>)> {
+    let mut paths = dir
+        .read_dir()
+        .with_context(|| format!("Failed to read folder `{}`", dir.display()))?
+        .filter_map(|f| f.ok().map(|f| f.path()))
+        .filter(|p| p.extension() == Some("vtf".as_ref()))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut crosshairs = Vec::new();
+    let mut errors = Vec::new();
+
+    for path in paths {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+
+        match texture::decode(&path) {
+            Ok(image) => crosshairs.push((name, image)),
+            Err(e) => errors.push(format!("Skipping {}; {}", name, e)),
+        }
+    }
+
+    Ok((crosshairs, errors))
+}
+
+/// A grid of `crosshairs`, each above its name.
+pub fn render(crosshairs: &[NamedImage], options: &Options) -> Result<RgbaImage> {
+    if options.cell_size > MAX_CELL_SIZE {
+        bail!(
+            "Cell size {} is over the maximum of {}",
+            options.cell_size,
+            MAX_CELL_SIZE
+        );
+    }
+
+    let cell_size = options.cell_size.max(1);
+    let cell_width = cell_size + 2 * PADDING;
+    let cell_height = cell_size + 2 * PADDING + LABEL_HEIGHT;
+
+    let columns = options.columns.clamp(1, crosshairs.len().max(1) as u32);
+    let rows = u32::try_from(crosshairs.len())?.div_ceil(columns).max(1);
+
+    let (width, height) = match (
+        columns.checked_mul(cell_width),
+        rows.checked_mul(cell_height),
+    ) {
+        (Some(w), Some(h))
+            if (w as usize)
+                .checked_mul(h as usize)
+                .and_then(|n| n.checked_mul(4))
+                .is_some() =>
+        {
+            (w, h)
+        }
+        _ => bail!(
+            "Too many crosshairs for one sheet at cell size {}",
+            cell_size
+        ),
+    };
+
+    let mut sheet = RgbaImage::from_fn(width, height, |x, y| {
+        if y % cell_height >= cell_height - LABEL_HEIGHT {
+            LABEL_BACKGROUND
+        } else {
+            let [r, g, b] = options.background.pixel(x, y, width, height);
+            Rgba([r, g, b, 255])
+        }
+    });
+
+    for (i, (name, image)) in crosshairs.iter().enumerate() {
+        let (left, top) = (
+            (i as u32 % columns) * cell_width,
+            (i as u32 / columns) * cell_height,
+        );
+
+        let scaled = DynamicImage::ImageRgba8(image.clone())
+            .resize(cell_size, cell_size, FilterType::Triangle)
+            .to_rgba();
+
+        // Centre the crosshair in its cell.
+        let x = left + PADDING + cell_size.saturating_sub(scaled.width()) / 2;
+        let y = top + PADDING + cell_size.saturating_sub(scaled.height()) / 2;
+
+        for (sx, sy, pixel) in scaled.enumerate_pixels() {
+            let below = sheet.get_pixel(x + sx, y + sy).0;
+            let blended = preview::blend([below[0], below[1], below[2]], *pixel);
+            sheet.put_pixel(x + sx, y + sy, blended);
+        }
+
+        let label = font::fit(name, cell_width - 2 * PADDING, 1);
+        font::draw_text(
+            &mut sheet,
+            (left + cell_width.saturating_sub(font::text_width(&label, 1)) / 2) as i64,
+            (top + cell_height - LABEL_HEIGHT + PADDING) as i64,
+            &label,
+            1,
+            LABEL_COLOR,
+        );
+    }
+
+    Ok(sheet)
+}
+
+/// Writes a contact sheet of the crosshairs in `dir` to `path`, returning the crosshairs skipped.
+pub fn export(dir: &Path, path: &Path, options: &Options) -> Result<Vec<String>> {
+    let (crosshairs, errors) = load(dir)?;
+
+    if crosshairs.is_empty() {
+        bail!("No crosshairs to export in `{}`", dir.display());
+    }
+
+    render(&crosshairs, options)?
+        .save(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let crosshairs = (0..5)
+            .map(|i| {
+                (
+                    format!("{}", i),
+                    RgbaImage::from_pixel(8, 4, Rgba([255; 4])),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let options = Options {
+            cell_size: 16,
+            columns: 3,
+            background: Background::Black,
+        };
+
+        let sheet = super::render(&crosshairs, &options).unwrap();
+
+        let (cell_width, cell_height) = (16 + 2 * PADDING, 16 + 2 * PADDING + LABEL_HEIGHT);
+        assert_eq!(sheet.dimensions(), (3 * cell_width, 2 * cell_height));
+
+        // Scaled to 16x8 and centred, over the background.
+        assert_eq!(sheet.get_pixel(PADDING, PADDING + 4).0, [255; 4]);
+        assert_eq!(sheet.get_pixel(PADDING, PADDING + 3).0, [0, 0, 0, 255]);
+        assert_eq!(sheet.get_pixel(0, cell_height - 1), &LABEL_BACKGROUND);
+
+        let empty = Options {
+            cell_size: 0,
+            columns: 0,
+            background: Background::Black,
+        };
+        let sheet = super::render(&crosshairs, &empty).unwrap();
+        assert_eq!(sheet.width(), 1 + 2 * PADDING);
+
+        let huge = Options {
+            cell_size: MAX_CELL_SIZE + 1,
+            ..options
+        };
+        assert!(super::render(&crosshairs, &huge).is_err());
+    }
+
+    #[test]
+    fn bundled() {
+        let (crosshairs, errors) =
+            load(Path::new("resources/materials/vgui/replay/thumbnails")).unwrap();
+
+        assert!(errors.is_empty());
+        assert!(crosshairs.len() > 90);
+        assert!(crosshairs.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+}
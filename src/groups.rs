@@ -0,0 +1,202 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// Name of the groups file kept next to the binary.
+const FILE: &str = "groups.json";
+
+/// Named sets of weapon scripts to apply crosshairs to together, like `shotguns` or
+/// `Medic mains`, kept in the order they were made.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Groups {
+    entries: Vec<(String, Vec<String>)>,
+}
+
+impl Groups {
+    pub fn path() -> Result<PathBuf> {
+        Ok(crate::exe_dir()?.join(FILE))
+    }
+
+    /// The groups next to the binary, empty if there are none yet.
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::read(&path)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.write(&Self::path()?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        let s =
+            fs::read_to_string(path).with_context(|| format!("Failed to open {}", file_name))?;
+
+        Self::parse(&s).with_context(|| format!("Failed to parse {}", file_name))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        let json = json::parse(s)?;
+
+        if !json.is_object() {
+            bail!("Expected an object of groups");
+        }
+
+        let entries = json
+            .entries()
+            .map(|(name, scripts)| {
+                if !scripts.is_array() {
+                    bail!("`{}` isn't a list of weapon scripts", name);
+                }
+
+                let scripts = scripts
+                    .members()
+                    .map(|s| {
+                        s.as_str().map(str::to_string).ok_or_else(|| {
+                            anyhow!("`{}` has a weapon script that isn't a string", name)
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok((name.to_string(), scripts))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { entries })
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = json::JsonValue::new_object();
+
+        for (name, scripts) in &self.entries {
+            json[name.as_str()] = scripts.clone().into();
+        }
+
+        json::stringify_pretty(json, 4)
+    }
+
+    /// The weapon scripts of `name`, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.entries
+            .iter()
+            .find(|e| e.0.eq_ignore_ascii_case(name))
+            .map(|e| e.1.as_slice())
+    }
+
+    /// Adds or replaces the group `name`. Names can't have `/` or `|`, which menus read as
+    /// submenus and separators.
+    pub fn set(&mut self, name: &str, scripts: Vec<String>) -> Result<()> {
+        let name = name.trim();
+
+        if name.is_empty() {
+            bail!("Groups need a name");
+        }
+
+        if name.contains(['/', '|']) {
+            bail!("Group names can't have `/` or `|`");
+        }
+
+        if scripts.is_empty() {
+            bail!("No weapons for group `{}`", name);
+        }
+
+        let mut unique = Vec::new();
+        for script in scripts {
+            if !unique.contains(&script) {
+                unique.push(script);
+            }
+        }
+        let scripts = unique;
+
+        match self
+            .entries
+            .iter()
+            .position(|e| e.0.eq_ignore_ascii_case(name))
+        {
+            Some(i) => self.entries[i] = (name.to_string(), scripts),
+            None => self.entries.push((name.to_string(), scripts)),
+        }
+
+        Ok(())
+    }
+
+    /// Removes `name`, returning whether there was such a group.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|e| !e.0.eq_ignore_ascii_case(name));
+
+        self.entries.len() != len
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.entries.iter().map(|(n, s)| (n.as_str(), s.as_slice()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn groups() {
+        let mut groups = Groups::default();
+
+        groups
+            .set(
+                "Shotguns",
+                vec![
+                    "tf_weapon_shotgun_soldier".into(),
+                    "tf_weapon_shotgun_pyro".into(),
+                ],
+            )
+            .unwrap();
+        groups
+            .set("Medic mains", vec!["tf_weapon_crossbow".into()])
+            .unwrap();
+        groups
+            .set("shotguns", vec!["tf_weapon_shotgun_hwg".into()])
+            .unwrap();
+
+        // Replacing a group keeps its place.
+        assert_eq!(
+            groups.iter().map(|(n, _)| n).collect::<Vec<_>>(),
+            ["shotguns", "Medic mains"]
+        );
+        assert_eq!(
+            groups.get("SHOTGUNS").unwrap(),
+            ["tf_weapon_shotgun_hwg".to_string()]
+        );
+
+        assert_eq!(Groups::parse(&groups.to_json()).unwrap(), groups);
+
+        assert!(groups.set("a/b", vec!["x".into()]).is_err());
+        assert!(groups.set(" ", vec!["x".into()]).is_err());
+        assert!(groups.set("empty", Vec::new()).is_err());
+
+        assert!(groups.remove("medic mains"));
+        assert!(!groups.remove("medic mains"));
+        assert_eq!(groups.len(), 1);
+
+        assert!(Groups::parse("{\"a\": \"tf_weapon_smg\"}").is_err());
+    }
+}
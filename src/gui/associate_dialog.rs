@@ -0,0 +1,119 @@
+use crate::associations::{Association, Class, Slot};
+use crate::gui::field;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use fltk::{app, button, frame, group::Flex, input, menu, prelude::*, window};
+
+/// Asks for the class, slot and display name of one of the `unknown` weapon scripts.
+pub fn show(unknown: &[String]) -> Option<(String, Association)> {
+    let mut wind = window::Window::default()
+        .with_size(420, 230)
+        .with_label("Add association")
+        .center_screen();
+
+    let mut col = Flex::default_fill().column();
+    col.set_margin(5);
+
+    let row = field(&mut col, "Script");
+    let mut script = menu::Choice::default_fill();
+    for name in unknown {
+        script.add_choice(name);
+    }
+    script.set_value(0);
+    row.end();
+
+    let row = field(&mut col, "Class");
+    let mut class = menu::Choice::default_fill();
+    for c in Class::ALL {
+        class.add_choice(c.to_str());
+    }
+    class.set_value(0);
+    row.end();
+
+    let row = field(&mut col, "Slot");
+    let mut slot = menu::Choice::default_fill();
+    for s in Slot::ALL {
+        slot.add_choice(s.to_str());
+    }
+    slot.set_value(0);
+    row.end();
+
+    let row = field(&mut col, "Category");
+    let display = input::Input::default_fill();
+    row.end();
+
+    let row = field(&mut col, "Items");
+    let mut items = input::Input::default_fill();
+    items.set_tooltip("Comma separated list of the items using this script");
+    row.end();
+
+    frame::Frame::default_fill();
+
+    let mut row = Flex::default().row();
+    col.set_size(&row, 30);
+    frame::Frame::default_fill();
+    let mut ok_btn = button::Button::default_fill().with_label("Add");
+    row.set_size(&ok_btn, 100);
+    let mut cancel_btn = button::Button::default_fill().with_label("Cancel");
+    row.set_size(&cancel_btn, 100);
+    row.end();
+
+    col.end();
+    wind.end();
+
+    let accepted = Rc::new(Cell::new(false));
+
+    ok_btn.set_callback({
+        let accepted = accepted.clone();
+        let mut wind = wind.clone();
+
+        move |_| {
+            accepted.set(true);
+            wind.hide();
+        }
+    });
+
+    cancel_btn.set_callback({
+        let mut wind = wind.clone();
+
+        move |_| wind.hide()
+    });
+
+    wind.make_modal(true);
+    wind.show();
+
+    while wind.shown() {
+        app::wait();
+    }
+
+    if !accepted.get() {
+        return None;
+    }
+
+    let key = unknown.get(script.value() as usize)?.clone();
+
+    let display = match display.value().trim() {
+        "" => key.clone(),
+        d => d.to_string(),
+    };
+
+    let all = items
+        .value()
+        .split(',')
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    Some((
+        key,
+        Association {
+            class: Class::ALL[class.value() as usize],
+            slot: Slot::ALL[slot.value() as usize],
+            display,
+            all,
+        },
+    ))
+}
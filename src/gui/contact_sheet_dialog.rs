@@ -0,0 +1,118 @@
+use crate::contact_sheet::{Options, MAX_CELL_SIZE};
+use crate::gui::field;
+use crate::preview::Background;
+
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use fltk::{app, button, dialog, frame, group::Flex, input, menu, prelude::*, window};
+
+/// A whole number of at least 1 from `input`, and at most `max` if given.
+fn number(input: &input::IntInput, name: &str, max: Option<u32>) -> Result<u32> {
+    let value = input.value();
+
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&n| n >= 1 && !max.is_some_and(|max| n > max))
+        .ok_or_else(|| match max {
+            Some(max) => anyhow!("Invalid {} `{}`, expected 1 to {}", name, value, max),
+            None => anyhow!("Invalid {} `{}`, expected at least 1", name, value),
+        })
+}
+
+/// Asks for the layout of a contact sheet and where to save it.
+pub fn show() -> Result<Option<(Options, PathBuf)>> {
+    let defaults = Options::default();
+
+    let mut wind = window::Window::default()
+        .with_size(300, 170)
+        .with_label("Export contact sheet")
+        .center_screen();
+
+    let mut col = Flex::default_fill().column();
+    col.set_margin(5);
+
+    let row = field(&mut col, "Columns");
+    let mut columns = input::IntInput::default_fill();
+    columns.set_value(&defaults.columns.to_string());
+    row.end();
+
+    let row = field(&mut col, "Cell size");
+    let mut cell_size = input::IntInput::default_fill();
+    cell_size.set_value(&defaults.cell_size.to_string());
+    row.end();
+
+    let row = field(&mut col, "Background");
+    let mut background = menu::Choice::default_fill();
+    for name in Background::NAMES {
+        background.add_choice(name);
+    }
+    background.set_value(0);
+    row.end();
+
+    frame::Frame::default_fill();
+
+    let mut row = Flex::default().row();
+    col.set_size(&row, 30);
+    frame::Frame::default_fill();
+    let mut ok_btn = button::Button::default_fill().with_label("Export...");
+    row.set_size(&ok_btn, 100);
+    let mut cancel_btn = button::Button::default_fill().with_label("Cancel");
+    row.set_size(&cancel_btn, 100);
+    row.end();
+
+    col.end();
+    wind.end();
+
+    let accepted = Rc::new(Cell::new(false));
+
+    ok_btn.set_callback({
+        let accepted = accepted.clone();
+        let mut wind = wind.clone();
+
+        move |_| {
+            accepted.set(true);
+            wind.hide();
+        }
+    });
+
+    cancel_btn.set_callback({
+        let mut wind = wind.clone();
+
+        move |_| wind.hide()
+    });
+
+    wind.make_modal(true);
+    wind.show();
+
+    while wind.shown() {
+        app::wait();
+    }
+
+    if !accepted.get() {
+        return Ok(None);
+    }
+
+    let options = Options {
+        cell_size: number(&cell_size, "cell size", Some(MAX_CELL_SIZE))?,
+        columns: number(&columns, "column count", None)?,
+        background: Background::from_index(background.value().max(0) as usize),
+    };
+
+    let mut chooser = dialog::NativeFileChooser::new(dialog::FileDialogType::BrowseSaveFile);
+    chooser.set_filter("PNG\t*.png");
+    chooser.set_preset_file("crosshairs.png");
+    chooser.show();
+
+    let path = chooser.filename();
+
+    if path.as_os_str().is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some((options, path.with_extension("png"))))
+}
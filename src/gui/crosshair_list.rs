@@ -1,119 +1,285 @@
-use crate::gui::{Log, LogType, Message};
+use crate::catalogue::Catalogue;
+use crate::gui::{Loaded, Message};
+use crate::model::{CrosshairFilter, CrosshairItem, CrosshairRow, Model};
+use crate::thumbnails::{self, Cache};
 
-use std::io::Read;
-use std::path::{Path, PathBuf};
+use std::sync::mpsc;
 
-use anyhow::{bail, Context, Result};
-use fltk::{app::Sender, browser, group, prelude::*};
-
-#[derive(Clone)]
-pub struct CrosshairItem {
-    pub name: String,
-    pub path: PathBuf,
-    pub size: (i32, i32),
-}
+use anyhow::{Context, Result};
+use fltk::{
+    app::Sender, browser, button, enums, group, image::PngImage, input, menu, misc, prelude::*,
+};
 
 #[derive(Clone)]
 pub(crate) struct CrosshairList {
+    search: input::Input,
+    group: button::CheckButton,
+    outline: button::CheckButton,
+    label: menu::Choice,
+    favourites: button::CheckButton,
     list: browser::SelectBrowser,
+    progress: misc::Progress,
 }
 
 impl CrosshairList {
-    pub fn new() -> Self {
-        let row = group::Flex::default_fill().row();
+    pub fn new(s: Sender<Message>) -> Self {
+        let mut col = group::Flex::default_fill().column();
+
+        let mut row = group::Flex::default().row();
+        col.set_size(&row, 25);
 
-        let list = browser::SelectBrowser::default_fill();
+        let mut search = input::Input::default_fill();
+        search.set_tooltip("Crosshair name");
+        search.set_trigger(enums::CallbackTrigger::Changed);
+        search.emit(s, Message::CrosshairFilterChanged);
+
+        let mut group = button::CheckButton::default_fill().with_label("Group");
+        group.emit(s, Message::CrosshairFilterChanged);
+        row.set_size(&group, 65);
+
+        let mut outline = button::CheckButton::default_fill().with_label("OL");
+        outline.set_tooltip("Switch to the outline variant of the selected crosshair");
+        outline.emit(s, Message::OutlineToggled);
+        outline.deactivate();
+        row.set_size(&outline, 45);
 
         row.end();
 
-        Self { list }
+        let mut row = group::Flex::default().row();
+        col.set_size(&row, 25);
+
+        let mut label = menu::Choice::default_fill();
+        label.set_tooltip("Source or tag");
+        label.add_choice("Any source or tag");
+        label.set_value(0);
+        label.emit(s, Message::CrosshairFilterChanged);
+
+        let mut favourites = button::CheckButton::default_fill().with_label("Favourites");
+        favourites.emit(s, Message::CrosshairFilterChanged);
+        row.set_size(&favourites, 90);
+
+        row.end();
+
+        let mut list = browser::SelectBrowser::default_fill();
+        list.emit(s, Message::CrosshairListClicked);
+
+        let mut progress = misc::Progress::default_fill();
+        col.set_size(&progress, 20);
+
+        col.end();
+
+        Self {
+            search,
+            group,
+            outline,
+            label,
+            favourites,
+            list,
+            progress,
+        }
     }
 
-    pub fn init(&mut self, log: &mut Log, s: Sender<Message>) -> Result<()> {
-        let mut crosshair_dir =
-            std::env::current_exe().with_context(|| "Failed to get current executable path")?;
-        crosshair_dir.pop();
-        crosshair_dir.push("materials/vgui/replay/thumbnails");
+    pub fn filter(&self, model: &Model) -> CrosshairFilter {
+        let label = match self.label.value() {
+            i if i > 0 => model.catalogue().labels().get(i as usize - 1).cloned(),
+            _ => None,
+        };
 
-        if !crosshair_dir.exists() {
-            bail!("Failed to find `materials/vgui/replay/thumbnails` folder");
+        CrosshairFilter {
+            text: self.search.value(),
+            group: self.group.is_checked(),
+            favourites: self.favourites.is_checked(),
+            label,
         }
+    }
 
-        for file in crosshair_dir.read_dir().with_context(|| {
-            format!(
-                "Failed to read folder `{}`",
-                crosshair_dir.file_name().unwrap().to_string_lossy()
-            )
-        })? {
-            match file {
-                Ok(crosshair) => {
-                    if crosshair.path().extension() == Some(std::ffi::OsStr::new("vtf")) {
-                        let crosshair_name = crosshair.file_name().to_string_lossy().into_owned();
-
-                        self.list.add_with_data(
-                            &crosshair_name,
-                            CrosshairItem {
-                                name: crosshair_name.clone(),
-                                path: crosshair.path(),
-                                size: (0, 0),
-                            },
-                        );
-                    }
-                }
-                Err(e) => log.log(LogType::Error, e),
+    /// Lists the sources and tags of `catalogue` to filter by, keeping the chosen one if it's
+    /// still used.
+    pub fn set_labels(&mut self, catalogue: &Catalogue) {
+        let chosen = self.label.choice();
+
+        self.label.clear();
+        self.label.add_choice("Any source or tag");
+        self.label.set_value(0);
+
+        for (i, label) in catalogue.labels().iter().enumerate() {
+            // Slashes would otherwise make submenus.
+            self.label.add_choice(&label.replace('/', "\\/"));
+
+            if chosen.as_deref() == Some(label.as_str()) {
+                self.label.set_value(i as i32 + 1);
             }
         }
+    }
 
-        for i in 1..=self.list.size() {
-            let crosshair = self.get_item(i).unwrap();
-
-            match self.vtf_to_image(&crosshair.path) {
-                Ok(mut image) => {
-                    self.list.set_data(
-                        i,
-                        CrosshairItem {
-                            size: (image.width(), image.height()),
-                            ..crosshair
-                        },
-                    );
-
-                    image.scale(32, 32, true, true);
-                    self.list.set_icon(i, Some(image));
-                }
-                Err(e) => {
-                    log.log(
-                        LogType::Error,
-                        format!("Skipping {}; {}", crosshair.name, e),
-                    );
-                }
+    /// Starts the progress bar for `total` crosshairs about to be added.
+    pub fn set_total(&mut self, total: usize) {
+        self.progress.set_minimum(0.0);
+        self.progress.set_maximum(total as f64);
+        self.progress.set_value(0.0);
+        self.update_progress_label();
+    }
+
+    pub fn set_loaded(&mut self, loaded: usize) {
+        self.progress.set_value(loaded as f64);
+        self.update_progress_label();
+    }
+
+    fn update_progress_label(&mut self) {
+        let (value, maximum) = (self.progress.value(), self.progress.maximum());
+
+        if value < maximum {
+            self.progress
+                .set_label(&format!("Loading crosshairs {}/{}", value, maximum));
+        } else {
+            self.progress.set_label(&format!("{} crosshairs", value));
+        }
+    }
+
+    /// Rebuilds every row, keeping the selected crosshair selected and the scroll position.
+    pub fn render(&mut self, model: &Model) {
+        let position = self.list.position();
+        self.list.clear();
+
+        for (line, row) in model.crosshair_rows().iter().enumerate() {
+            self.list.add(&model.crosshair_row(row));
+
+            let icon = match row {
+                CrosshairRow::Crosshair(i) => model.crosshair_icon(*i),
+                CrosshairRow::Family(_) => None,
+            };
+
+            if let Some(mut image) = icon.and_then(|png| PngImage::from_data(png).ok()) {
+                image.scale(thumbnails::SIZE as i32, thumbnails::SIZE as i32, true, true);
+                self.list.set_icon(line as i32 + 1, Some(image));
             }
         }
 
-        s.send(Message::Redraw);
+        if let Some(row) = model
+            .selected_crosshair_index()
+            .and_then(|i| model.crosshair_list_row(i))
+        {
+            self.list.select(row as i32 + 1);
+        }
 
-        Ok(())
+        self.list.set_position(position);
+        self.update_outline(model);
     }
 
-    fn vtf_to_image(&mut self, vtf_path: &Path) -> Result<fltk::image::PngImage> {
-        let mut crosshair_file = std::fs::File::open(&vtf_path)?;
+    /// Selects crosshair `i` if it's visible.
+    pub fn select(&mut self, model: &Model, i: usize) {
+        if let Some(row) = model.crosshair_list_row(i) {
+            self.list.select(row as i32 + 1);
+            self.list.middle_line(row as i32 + 1);
+        }
 
-        let mut buf = Vec::new();
-        crosshair_file.read_to_end(&mut buf)?;
+        self.update_outline(model);
+    }
 
-        let vtf = vtf::from_bytes(&mut buf)?.highres_image.decode(0)?;
+    /// Ticks the outline toggle for `OL` crosshairs, only allowing it when both variants exist.
+    pub fn update_outline(&mut self, model: &Model) {
+        let selected = model.selected_crosshair_index();
 
-        buf.clear();
+        self.outline.set_checked(
+            model
+                .selected_crosshair()
+                .is_some_and(|c| crate::model::crosshair_stem(&c.name).ends_with("OL")),
+        );
 
-        vtf.write_to(&mut buf, image::ImageFormat::PNG)?;
+        if selected.and_then(|i| model.outline_variant(i)).is_some() {
+            self.outline.activate();
+        } else {
+            self.outline.deactivate();
+        }
+    }
 
-        Ok(fltk::image::PngImage::from_data(&buf).unwrap())
+    /// The selected crosshair, as a model index.
+    pub fn selection(&self, model: &Model) -> Option<usize> {
+        match self.list.value() {
+            0 => None,
+            line => match model.crosshair_rows().get(line as usize - 1)? {
+                CrosshairRow::Crosshair(i) => Some(*i),
+                CrosshairRow::Family(_) => None,
+            },
+        }
     }
+}
+
+/// Reads the crosshairs in the crosshair folder, sending each one as soon as its thumbnail has
+/// been decoded or found in the thumbnail cache, tagged with the `generation` of the load.
+pub fn load(tx: &mpsc::Sender<Loaded>, s: Sender<Message>, generation: u64) -> Result<()> {
+    let crosshair_dir = crate::crosshair_dir()?;
 
-    fn get_item(&self, i: i32) -> Option<CrosshairItem> {
-        unsafe { self.list.data(i) }
+    let mut paths = Vec::new();
+
+    for file in crosshair_dir.read_dir().with_context(|| {
+        format!(
+            "Failed to read folder `{}`",
+            crosshair_dir.file_name().unwrap().to_string_lossy()
+        )
+    })? {
+        match file {
+            Ok(crosshair) => {
+                if crosshair.path().extension() == Some(std::ffi::OsStr::new("vtf")) {
+                    paths.push(crosshair.path());
+                }
+            }
+            Err(e) => tx.send(Loaded::Error(e.to_string()))?,
+        }
     }
 
-    pub fn selected(&self) -> Option<CrosshairItem> {
-        self.get_item(self.list.value())
+    match Catalogue::load(&crosshair_dir) {
+        Ok(catalogue) => tx.send(Loaded::Catalogue(generation, catalogue))?,
+        Err(e) => tx.send(Loaded::Error(format!("{:#}", e)))?,
     }
+
+    tx.send(Loaded::CrosshairCount(generation, paths.len()))?;
+    s.send(Message::Loaded);
+
+    let cache_dir = crate::exe_dir()?.join("cache/thumbnails");
+
+    let cache = match Cache::open(&cache_dir) {
+        Ok(mut c) => {
+            if let Err(e) = c.prune(&paths) {
+                tx.send(Loaded::Error(format!(
+                    "Failed to prune thumbnails; {:#}",
+                    e
+                )))?;
+            }
+
+            Some(c)
+        }
+        Err(e) => {
+            tx.send(Loaded::Error(format!("Not caching thumbnails; {:#}", e)))?;
+            None
+        }
+    };
+
+    thumbnails::load_all(&paths, cache.as_ref(), |path, thumbnail| {
+        let mut crosshair = CrosshairItem {
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            path: path.to_path_buf(),
+            size: (0, 0),
+        };
+
+        let icon = match thumbnail {
+            Ok(t) => {
+                crosshair.size = t.size;
+                Some(t.png)
+            }
+            Err(e) => {
+                let _ = tx.send(Loaded::Error(format!("Skipping {}; {}", crosshair.name, e)));
+                None
+            }
+        };
+
+        if tx
+            .send(Loaded::Crosshair(generation, crosshair, icon))
+            .is_ok()
+        {
+            s.send(Message::Loaded);
+        }
+    });
+
+    Ok(())
 }
@@ -0,0 +1,103 @@
+use crate::catalogue::CrosshairMeta;
+use crate::gui::field;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use fltk::{app, button, frame, group::Flex, input, prelude::*, window};
+
+/// Edits the catalogue details of crosshair `name`.
+pub fn show(name: &str, meta: &CrosshairMeta) -> Option<CrosshairMeta> {
+    let mut wind = window::Window::default()
+        .with_size(420, 260)
+        .with_label(&format!("Details of {}", name))
+        .center_screen();
+
+    let mut col = Flex::default_fill().column();
+    col.set_margin(5);
+
+    let row = field(&mut col, "Name");
+    let mut display = input::Input::default_fill();
+    display.set_value(&meta.display);
+    row.end();
+
+    let row = field(&mut col, "Author");
+    let mut author = input::Input::default_fill();
+    author.set_value(&meta.author);
+    row.end();
+
+    let row = field(&mut col, "Source");
+    let mut source = input::Input::default_fill();
+    source.set_tooltip("Pack or game the crosshair came from, like Quake Live or CPMA");
+    source.set_value(&meta.source);
+    row.end();
+
+    let row = field(&mut col, "Tags");
+    let mut tags = input::Input::default_fill();
+    tags.set_tooltip("Comma separated list of tags");
+    tags.set_value(&meta.tags.join(", "));
+    row.end();
+
+    let mut favourite = button::CheckButton::default().with_label("Favourite");
+    favourite.set_checked(meta.favourite);
+    col.set_size(&favourite, 30);
+
+    frame::Frame::default_fill();
+
+    let mut row = Flex::default().row();
+    col.set_size(&row, 30);
+    frame::Frame::default_fill();
+    let mut ok_btn = button::Button::default_fill().with_label("Save");
+    row.set_size(&ok_btn, 100);
+    let mut cancel_btn = button::Button::default_fill().with_label("Cancel");
+    row.set_size(&cancel_btn, 100);
+    row.end();
+
+    col.end();
+    wind.end();
+
+    let accepted = Rc::new(Cell::new(false));
+
+    ok_btn.set_callback({
+        let accepted = accepted.clone();
+        let mut wind = wind.clone();
+
+        move |_| {
+            accepted.set(true);
+            wind.hide();
+        }
+    });
+
+    cancel_btn.set_callback({
+        let mut wind = wind.clone();
+
+        move |_| wind.hide()
+    });
+
+    wind.make_modal(true);
+    wind.show();
+
+    while wind.shown() {
+        app::wait();
+    }
+
+    if !accepted.get() {
+        return None;
+    }
+
+    let tags = tags
+        .value()
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    Some(CrosshairMeta {
+        display: display.value().trim().to_string(),
+        author: author.value().trim().to_string(),
+        source: source.value().trim().to_string(),
+        tags,
+        favourite: favourite.is_checked(),
+    })
+}
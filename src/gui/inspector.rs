@@ -0,0 +1,109 @@
+use crate::model::CrosshairItem;
+use crate::texture::{flags, TextureInfo};
+
+use anyhow::Result;
+use fltk::{enums, frame, group::Flex, image::RgbImage, prelude::*, text};
+
+/// Texture metadata of the selected crosshair, to tell why it looks wrong in game.
+#[derive(Clone)]
+pub(crate) struct Inspector {
+    lowres: frame::Frame,
+    text: text::TextDisplay,
+}
+
+impl Inspector {
+    pub fn new() -> Self {
+        let mut col = Flex::default_fill().column();
+
+        let lowres = frame::Frame::default_fill();
+        col.set_size(&lowres, 40);
+
+        let mut text = text::TextDisplay::default_fill();
+        text.set_text_font(enums::Font::Courier);
+        text.set_scrollbar_align(enums::Align::Right);
+        text.set_buffer(text::TextBuffer::default());
+
+        col.end();
+
+        Self { lowres, text }
+    }
+
+    /// Shows `crosshair`, along with `users`, the weapons using it.
+    pub fn show(&mut self, crosshair: &CrosshairItem, users: Vec<String>) -> Result<()> {
+        let info = TextureInfo::open(&crosshair.path)?;
+        let header = &info.header;
+
+        match &info.lowres {
+            Some(lowres) => {
+                let mut image = RgbImage::new(
+                    lowres.as_raw(),
+                    lowres.width() as i32,
+                    lowres.height() as i32,
+                    enums::ColorDepth::Rgba8,
+                )?;
+                image.scale(32, 32, true, true);
+
+                self.lowres.set_label("");
+                self.lowres.set_image(Some(image));
+            }
+            None => {
+                self.lowres.set_image(None::<RgbImage>);
+                self.lowres.set_label("No low-res image");
+            }
+        }
+
+        let flag_names = flags::names(header.flags);
+
+        let warnings = if info.warnings.is_empty() {
+            "None".to_string()
+        } else {
+            format!("\n  - {}", info.warnings.join("\n  - "))
+        };
+
+        let users = if users.is_empty() {
+            "None".to_string()
+        } else {
+            format!("\n  - {}", users.join("\n  - "))
+        };
+
+        self.text.buffer().unwrap().set_text(&format!(
+            "\
+{}\n
+Used by: {}\n
+Version: {}.{}
+Format: {}
+Size: {}x{}
+Frames: {}
+Mipmaps: {}
+Flags: {}
+Reflectivity: {:.2} {:.2} {:.2}
+Low-res: {} {}x{}\n
+Warnings: {}",
+            crosshair.name,
+            users,
+            header.version.0,
+            header.version.1,
+            header.format.to_str(),
+            header.width,
+            header.height,
+            header.frames,
+            header.mipmap_count,
+            if flag_names.is_empty() {
+                "None".to_string()
+            } else {
+                flag_names.join(", ")
+            },
+            header.reflectivity[0],
+            header.reflectivity[1],
+            header.reflectivity[2],
+            header.lowres_format.to_str(),
+            header.lowres_width,
+            header.lowres_height,
+            warnings,
+        ));
+
+        self.lowres.redraw();
+
+        Ok(())
+    }
+}
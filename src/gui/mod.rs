@@ -1,14 +1,34 @@
-pub mod crosshair_list;
+mod associate_dialog;
+mod contact_sheet_dialog;
+mod crosshair_list;
+mod details_dialog;
+mod inspector;
+mod preview;
+mod simulate_dialog;
 mod weapon_list;
 
-use crate::{ExplosionEffect, WeaponFile, USES_EXPLOSION};
+use crate::associations::{self, Associations};
+use crate::catalogue::Catalogue;
+use crate::class_cfg;
+use crate::groups::Groups;
+use crate::localization::Localization;
+use crate::manifest::{self, Change, Manifest};
+use crate::model::{self, CrosshairItem, DisplayMode, Model, Weapon};
+use crate::rules::{self, Loadout, Rule};
+use crate::{
+    contact_sheet, loadout_card, overrides, prune, texture, ExplosionEffect, WeaponFile,
+    USES_EXPLOSION,
+};
 use crosshair_list::CrosshairList;
+use inspector::Inspector;
+use preview::Preview;
 use weapon_list::WeaponList;
 
 use std::path::Path;
+use std::sync::mpsc;
 
 use anyhow::{anyhow, bail, Result};
-use fltk::{app, button, enums, group::Flex, menu, prelude::*, text, window};
+use fltk::{app, button, dialog, enums, frame, group::Flex, menu, prelude::*, text, window};
 
 #[derive(Clone, Copy)]
 pub enum LogType {
@@ -46,6 +66,60 @@ impl Log {
     }
 }
 
+/// A row of a dialog, starting with `label`, for the caller to add an input to and end.
+fn field(col: &mut Flex, label: &str) -> Flex {
+    let mut row = Flex::default().row();
+    col.set_size(&row, 30);
+
+    let frame = frame::Frame::default()
+        .with_label(label)
+        .with_align(enums::Align::Left | enums::Align::Inside);
+    row.set_size(&frame, 90);
+
+    row
+}
+
+/// Logs the installed files edited or removed outside of the tool since it last wrote them.
+fn log_changes(log: &mut Log) -> Result<()> {
+    let root = crate::exe_dir()?;
+
+    for change in Manifest::load(&root)?.changes(&root) {
+        match change {
+            Change::Modified(file) => log.log(
+                LogType::Info,
+                format!("{} was edited outside of crosshair-switcher", file),
+            ),
+            Change::Missing(file) => log.log(
+                LogType::Info,
+                format!("{} was removed outside of crosshair-switcher", file),
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Logs the weapon scripts another mod in the custom folder wins over, so edits to them do
+/// nothing in game.
+fn log_overrides(log: &mut Log) -> Result<()> {
+    let ours = crate::exe_dir()?;
+    let (overrides, _) = overrides::scan(&ours)?;
+
+    let lost = overrides.iter().filter(|o| o.winner() != ours).count();
+
+    if lost > 0 {
+        log.log(
+            LogType::Info,
+            format!(
+                "{} weapon script(s) are overridden by other mods; see Weapons > Check other mods",
+                lost
+            ),
+        );
+    }
+
+    Ok(())
+}
+
 macro_rules! error_log {
     ($log:expr, $fun:expr) => {
         if let Err(e) = $fun {
@@ -60,66 +134,235 @@ pub enum ButtonMsg {
     ToClass,
     ToSlot,
     ToAll,
+    ToGroup,
 }
 
 #[derive(Clone, Copy)]
 pub enum Message {
     WeaponListClicked,
+    WeaponFilterChanged,
+    CrosshairListClicked,
+    CrosshairFilterChanged,
+    OutlineToggled,
+    EditCrosshairDetails,
+    ToggleFavourite,
     ButtonClicked(ButtonMsg),
     CrosshairRadioClicked,
     ExplosionRadioClicked,
-    Redraw,
+    AddAssociation,
+    Simulate,
+    ExportContactSheet,
+    PruneCrosshairs,
+    RestoreCrosshairs,
+    CheckOverrides,
+    ExportClassCfgs,
+    AddRule,
+    ApplyRules,
+    SaveGroup,
+    DeleteGroup,
+    ExportLoadoutCard,
+    PreviewChanged,
+    LoadScreenshot,
+    Loaded,
+}
+
+/// Data read off the main thread, picked up on `Message::Loaded`. Crosshair data carries the
+/// generation of the load it's from, so a reload can drop what an older load still sends.
+pub enum Loaded {
+    Weapons(Vec<Weapon>),
+    Localization(Localization),
+    Catalogue(u64, Catalogue),
+    CrosshairCount(u64, usize),
+    Crosshair(u64, CrosshairItem, Option<Vec<u8>>),
+    Error(String),
 }
 
 pub struct App {
     app: app::App,
+    associations: Associations,
+    groups: Groups,
+    model: Model,
 
     weapon_list: WeaponList,
     info: text::TextDisplay,
+    inspector: Inspector,
+    preview: Preview,
     crosshair_radio: button::RadioRoundButton,
     explosion_input: menu::Choice,
-    button_group: (button::Button, button::Button, button::Button),
+    group_input: menu::Choice,
+    button_group: (
+        button::Button,
+        button::Button,
+        button::Button,
+        button::Button,
+    ),
     crosshair_list: CrosshairList,
     log: Log,
 
     s: app::Sender<Message>,
     r: app::Receiver<Message>,
+    loaded_tx: mpsc::Sender<Loaded>,
+    loaded_rx: mpsc::Receiver<Loaded>,
+    /// Generation of the latest crosshair load.
+    crosshair_generation: u64,
 }
 
 impl App {
     pub fn new(title: &str) -> Self {
         let app = app::App::default().with_scheme(app::Scheme::Gtk);
+        let associations = Associations::load();
+        let groups = Groups::load();
         let theme = fltk_theme::ColorTheme::new(fltk_theme::color_themes::BLACK_THEME);
         theme.apply();
 
         let mut wind = window::Window::default()
-            .with_size(900, 800)
+            .with_size(1100, 800)
             .with_label(title)
             .center_screen();
-        wind.size_range(900, 600, 0, 0);
+        wind.size_range(1000, 600, 0, 0);
         wind.make_resizable(true);
 
         let (s, r) = app::channel();
+        let (loaded_tx, loaded_rx) = mpsc::channel();
 
         let mut main_column = Flex::default_fill().column();
         main_column.set_margin(5);
 
-        let (weapon_list, info, crosshair_radio, explosion_input, button_group, crosshair_list) = {
+        let mut menu_bar = menu::MenuBar::default();
+        main_column.set_size(&menu_bar, 25);
+        menu_bar.add_emit(
+            "&Weapons/Add association...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::AddAssociation,
+        );
+        menu_bar.add_emit(
+            "&Weapons/Export loadout card...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::ExportLoadoutCard,
+        );
+        menu_bar.add_emit(
+            "&Weapons/Check other mods...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::CheckOverrides,
+        );
+        menu_bar.add_emit(
+            "&Weapons/Export class configs for sv_pure...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::ExportClassCfgs,
+        );
+        menu_bar.add_emit(
+            "&Weapons/Add rule...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::AddRule,
+        );
+        menu_bar.add_emit(
+            "&Weapons/Apply loadout rules...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::ApplyRules,
+        );
+        menu_bar.add_emit(
+            "&Weapons/Save selection as group...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::SaveGroup,
+        );
+        menu_bar.add_emit(
+            "&Weapons/Delete group...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::DeleteGroup,
+        );
+        menu_bar.add_emit(
+            "&Crosshairs/In-game preview...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::Simulate,
+        );
+        menu_bar.add_emit(
+            "&Crosshairs/Edit details...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::EditCrosshairDetails,
+        );
+        menu_bar.add_emit(
+            "&Crosshairs/Toggle favourite\t",
+            enums::Shortcut::Ctrl | 'd',
+            menu::MenuFlag::Normal,
+            s,
+            Message::ToggleFavourite,
+        );
+        menu_bar.add_emit(
+            "&Crosshairs/Export contact sheet...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::ExportContactSheet,
+        );
+        menu_bar.add_emit(
+            "&Crosshairs/Prune unused crosshairs...\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::PruneCrosshairs,
+        );
+        menu_bar.add_emit(
+            "&Crosshairs/Restore pruned crosshairs\t",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            s,
+            Message::RestoreCrosshairs,
+        );
+
+        let (
+            weapon_list,
+            info,
+            inspector,
+            preview,
+            crosshair_radio,
+            explosion_input,
+            group_input,
+            button_group,
+            crosshair_list,
+        ) = {
             let row = Flex::default_fill().row();
 
             let weapon_list = WeaponList::new(s);
 
             let mut col = Flex::default_fill().column();
 
+            let info_row = Flex::default_fill().row();
+
             let mut info = text::TextDisplay::default_fill();
             info.set_text_font(enums::Font::Courier);
             info.set_scrollbar_align(enums::Align::Right);
             info.set_buffer(text::TextBuffer::default());
 
+            let preview = Preview::new(s);
+
+            let inspector = Inspector::new();
+
+            info_row.end();
+
             let row_2 = Flex::default().row();
-            col.set_size(&row_2, 230);
+            col.set_size(&row_2, 265);
 
-            let (crosshair_radio, explosion_input, button_group) = {
+            let (crosshair_radio, explosion_input, group_input, button_group) = {
                 let mut col = Flex::default_fill().column();
 
                 let mut crosshair_radio =
@@ -140,31 +383,48 @@ impl App {
                 apply_btn.emit(s, Message::ButtonClicked(ButtonMsg::Apply));
 
                 let mut apply_class_btn = button::Button::default_fill()
-                    .with_label("...to all weapons of this class")
+                    .with_label("...to listed weapons of this class")
                     .with_align(enums::Align::Left | enums::Align::Inside);
                 apply_class_btn.emit(s, Message::ButtonClicked(ButtonMsg::ToClass));
                 col.set_size(&apply_class_btn, 30);
 
                 let mut apply_slot_btn = button::Button::default_fill()
-                    .with_label("...to all weapons of this slot")
+                    .with_label("...to listed weapons of this slot")
                     .with_align(enums::Align::Left | enums::Align::Inside);
                 apply_slot_btn.emit(s, Message::ButtonClicked(ButtonMsg::ToSlot));
                 col.set_size(&apply_slot_btn, 30);
 
                 let mut apply_all_btn = button::Button::default_fill()
-                    .with_label("...to all weapons")
+                    .with_label("...to all listed weapons")
                     .with_align(enums::Align::Left | enums::Align::Inside);
                 apply_all_btn.emit(s, Message::ButtonClicked(ButtonMsg::ToAll));
                 col.set_size(&apply_all_btn, 30);
 
+                let group_row = Flex::default_fill().row();
+                col.set_size(&group_row, 30);
+
+                let mut apply_group_btn = button::Button::default_fill()
+                    .with_label("...to group")
+                    .with_align(enums::Align::Left | enums::Align::Inside);
+                apply_group_btn.emit(s, Message::ButtonClicked(ButtonMsg::ToGroup));
+
+                let group_input = menu::Choice::default_fill();
+
+                group_row.end();
+
                 col.end();
 
-                let button_group = (apply_class_btn, apply_slot_btn, apply_all_btn);
+                let button_group = (
+                    apply_class_btn,
+                    apply_slot_btn,
+                    apply_all_btn,
+                    apply_group_btn,
+                );
 
-                (crosshair_radio, explosion_input, button_group)
+                (crosshair_radio, explosion_input, group_input, button_group)
             };
 
-            let crosshair_list = CrosshairList::new();
+            let crosshair_list = CrosshairList::new(s);
 
             row_2.end();
 
@@ -174,14 +434,17 @@ impl App {
             (
                 weapon_list,
                 info,
+                inspector,
+                preview,
                 crosshair_radio,
                 explosion_input,
+                group_input,
                 button_group,
                 crosshair_list,
             )
         };
 
-        let log = {
+        let mut log = {
             let row = Flex::default().row();
             main_column.set_size(&row, 200);
 
@@ -192,57 +455,99 @@ impl App {
             log
         };
 
+        let associations = match associations {
+            Ok(a) => a,
+            Err(e) => {
+                // A broken associations.json shouldn't lose the built-in weapons too.
+                log.log(LogType::Error, format!("{:#}", e));
+                Associations::builtin().unwrap_or_default()
+            }
+        };
+
+        let groups = match groups {
+            Ok(g) => g,
+            Err(e) => {
+                log.log(LogType::Error, format!("{:#}", e));
+                Groups::default()
+            }
+        };
+
         main_column.end();
 
         wind.end();
         wind.show();
 
-        Self {
+        let mut app = Self {
             app,
+            associations,
+            groups,
+            model: Model::default(),
 
             weapon_list,
             info,
+            inspector,
+            preview,
             crosshair_radio,
             explosion_input,
+            group_input,
             button_group,
             crosshair_list,
             log,
 
             s,
             r,
-        }
+            loaded_tx,
+            loaded_rx,
+            crosshair_generation: 0,
+        };
+
+        app.render_groups();
+
+        app
     }
 
-    fn display_info(&mut self, weapon_file: &WeaponFile) -> Result<()> {
-        let weapon = self.weapon_list.find_value(&weapon_file.name)?;
+    fn display_info(&mut self, weapon: &Weapon) -> Result<()> {
+        let weapon_file = &weapon.file;
+
+        let association = match &weapon.association {
+            Some(a) => a,
+            None => {
+                self.info.buffer().unwrap().set_text(&format!(
+                    "\
+Class: Unknown\n
+Weapon Class: {}\n
+Crosshair: {}\n
+This weapon script has no associations.
+Use `Weapons > Add association...` to give it a class and slot.",
+                    weapon_file.name, weapon_file.crosshair,
+                ));
 
-        let slot_type = match weapon.1["slot"].as_u8().unwrap() {
-            1 => "Primary",
-            2 => "Secondary",
-            3 => "Melee",
-            4 => "PDA",
-            5 => "PDA",
-            9 => "Other",
-            _ => "",
+                return Ok(());
+            }
         };
 
+        let mut stats = weapon_file.data.lines().join("\n\n");
+        if !stats.is_empty() {
+            stats.push_str("\n\n");
+        }
+
         self.info.buffer().unwrap().set_text(&format!(
             "\
+Name: {}\n
 Class: {}\n
 Weapon Class: {}\n
 Category: {}\n
 Slot: {}\n
+{}\
 Affected Weapons:
   - {}",
-            weapon.1["class"],
-            weapon.0,
-            weapon.1["display"],
-            slot_type,
-            weapon.1["all"]
-                .members()
-                .map(|e| e.as_str().unwrap())
-                .collect::<Vec<_>>()
-                .join("\n  - ")
+            weapon.name(self.model.localization()),
+            association.class,
+            weapon_file.name,
+            association.display,
+            association.slot,
+            stats,
+            association.all.join("\n  - ")
         ));
 
         Ok(())
@@ -288,13 +593,15 @@ Affected Weapons:
 
     fn change_crosshair(&mut self, weapon: &WeaponFile) -> Result<()> {
         let selected_crosshair = self
-            .crosshair_list
-            .selected()
+            .model
+            .selected_crosshair()
+            .cloned()
             .ok_or_else(|| anyhow!("No crosshair selected"))?;
 
         let new_weapon_file = weapon.replace_crosshair(&selected_crosshair)?;
 
         std::fs::write(&weapon.path, new_weapon_file)?;
+        manifest::record_write(&weapon.path, false)?;
 
         self.log.log(
             LogType::Info,
@@ -326,6 +633,7 @@ Affected Weapons:
         let new_weapon_file = weapon.replace_explosion(&explosion)?;
 
         std::fs::write(&weapon.path, new_weapon_file)?;
+        manifest::record_write(&weapon.path, false)?;
 
         self.log.log(
             LogType::Info,
@@ -340,125 +648,853 @@ Affected Weapons:
         Ok(())
     }
 
-    fn apply_crosshairs(&mut self, weapons: Vec<(i32, WeaponFile)>) -> Result<()> {
+    fn apply_crosshairs(&mut self, weapons: Vec<usize>) -> Result<()> {
         if weapons.is_empty() {
             bail!("No weapon selected");
         }
 
-        for (i, weapon) in weapons {
+        for i in weapons {
+            let weapon = match self.model.weapon(i) {
+                Some(w) => w.file.clone(),
+                None => continue,
+            };
+
             error_log!(self.log, self.change_crosshair(&weapon));
-            error_log!(self.log, self.weapon_list.update_weapon(i, &weapon));
+            error_log!(self.log, self.model.reload_weapon(i));
+            self.weapon_list.update_row(&self.model, i);
+        }
+
+        // Usage counts changed along with the scripts.
+        self.crosshair_list.render(&self.model);
+
+        let users = self.crosshair_users();
+
+        if let Some(crosshair) = self.model.selected_crosshair() {
+            error_log!(self.log, self.inspector.show(crosshair, users));
+        }
+
+        Ok(())
+    }
+
+    fn apply_explosion(&mut self) -> Result<()> {
+        let (i, weapon) = self
+            .model
+            .current_weapon()
+            .map(|(i, w)| (i, w.clone()))
+            .ok_or_else(|| anyhow!("No selected item"))?;
+
+        if !weapon.uses_explosion() {
+            bail!("{} doesn't use explosions", weapon.file.name);
+        }
+
+        error_log!(self.log, self.change_explosion(&weapon.file));
+        error_log!(self.log, self.model.reload_weapon(i));
+        self.weapon_list.update_row(&self.model, i);
+
+        Ok(())
+    }
+
+    fn weapon_clicked(&mut self) {
+        let (current, selected) = self.weapon_list.selection(&self.model);
+        self.model.select_weapons(current, selected);
+
+        if let Some((_, weapon)) = self.model.current_weapon() {
+            let weapon = weapon.clone();
+
+            error_log!(self.log, self.display_info(&weapon));
+            error_log!(self.log, self.set_explosion_choice(&weapon.file));
+        }
+    }
+
+    fn filter_weapons(&mut self) {
+        self.model.set_filter(self.weapon_list.filter());
+        self.weapon_list.render(&self.model);
+    }
+
+    fn crosshair_clicked(&mut self) {
+        let selection = self.crosshair_list.selection(&self.model);
+        self.select_crosshair(selection);
+    }
+
+    fn select_crosshair(&mut self, i: Option<usize>) {
+        self.model.select_crosshair(i);
+        self.crosshair_list.update_outline(&self.model);
+
+        let users = self.crosshair_users();
+
+        if let Some(crosshair) = self.model.selected_crosshair() {
+            error_log!(self.log, self.inspector.show(crosshair, users));
+            error_log!(self.log, self.preview.show(crosshair));
+        }
+    }
+
+    /// The weapons using the selected crosshair, as `Class Name (script)` lines.
+    fn crosshair_users(&self) -> Vec<String> {
+        let users = match self.model.selected_crosshair_index() {
+            Some(i) => self.model.crosshair_users(i),
+            None => &[],
+        };
+
+        users
+            .iter()
+            .filter_map(|w| self.model.weapon(*w))
+            .map(|w| {
+                let name = w.name(self.model.localization());
+                format!("{} {} ({})", w.class_label(), name, w.file.name)
+            })
+            .collect()
+    }
+
+    fn filter_crosshairs(&mut self) {
+        let filter = self.crosshair_list.filter(&self.model);
+
+        self.model.set_crosshair_filter(filter);
+        self.crosshair_list.render(&self.model);
+    }
+
+    fn edit_crosshair_details(&mut self) -> Result<()> {
+        let i = self
+            .model
+            .selected_crosshair_index()
+            .ok_or_else(|| anyhow!("No crosshair selected"))?;
+
+        let name = &self.model.crosshairs()[i].name;
+        let meta = self.model.crosshair_meta(i).cloned().unwrap_or_default();
+
+        if let Some(meta) = details_dialog::show(name, &meta) {
+            self.model.set_crosshair_meta(i, meta);
+            self.save_catalogue()?;
+        }
+
+        Ok(())
+    }
+
+    fn toggle_favourite(&mut self) -> Result<()> {
+        let i = self
+            .model
+            .selected_crosshair_index()
+            .ok_or_else(|| anyhow!("No crosshair selected"))?;
+
+        let mut meta = self.model.crosshair_meta(i).cloned().unwrap_or_default();
+        meta.favourite = !meta.favourite;
+
+        self.model.set_crosshair_meta(i, meta);
+        self.save_catalogue()
+    }
+
+    /// Writes the catalogue next to the crosshairs and shows the changes.
+    fn save_catalogue(&mut self) -> Result<()> {
+        self.crosshair_list.set_labels(self.model.catalogue());
+        self.filter_crosshairs();
+
+        self.model.catalogue().save(&crate::crosshair_dir()?)
+    }
+
+    /// Switches between the plain and `OL` outline version of the selected crosshair.
+    fn toggle_outline(&mut self) {
+        let variant = self
+            .model
+            .selected_crosshair_index()
+            .and_then(|i| self.model.outline_variant(i));
+
+        if let Some(i) = variant {
+            self.select_crosshair(Some(i));
+            self.crosshair_list.select(&self.model, i);
+        }
+    }
+
+    fn set_display_mode(&mut self, mode: DisplayMode) {
+        let crosshairs = mode == DisplayMode::Crosshairs;
+
+        for btn in [
+            &mut self.button_group.0,
+            &mut self.button_group.1,
+            &mut self.button_group.2,
+            &mut self.button_group.3,
+        ] {
+            if crosshairs {
+                btn.activate();
+            } else {
+                btn.deactivate();
+            }
+        }
+
+        self.model.set_display_mode(mode);
+        self.weapon_list.refresh(&self.model);
+    }
+
+    fn receive_loaded(&mut self) {
+        let mut new_crosshairs = false;
+        let was_loaded = self.model.crosshairs_loaded();
+        let generation = self.crosshair_generation;
+
+        while let Ok(loaded) = self.loaded_rx.try_recv() {
+            match loaded {
+                Loaded::Weapons(weapons) => {
+                    self.model.set_weapons(weapons);
+                    self.weapon_list.render(&self.model);
+                }
+                Loaded::Localization(localization) => self.model.set_localization(localization),
+                Loaded::Catalogue(g, catalogue) if g == generation => {
+                    self.model.set_catalogue(catalogue);
+                    self.crosshair_list.set_labels(self.model.catalogue());
+                }
+                Loaded::CrosshairCount(g, n) if g == generation => {
+                    self.model.set_crosshair_count(n);
+                    self.crosshair_list.set_total(n);
+                }
+                Loaded::Crosshair(g, crosshair, icon) if g == generation => {
+                    self.model.push_crosshair(crosshair, icon);
+                    new_crosshairs = true;
+                }
+                // From a load since replaced by a reload.
+                Loaded::Catalogue(..) | Loaded::CrosshairCount(..) | Loaded::Crosshair(..) => {}
+                Loaded::Error(e) => self.log.log(LogType::Error, e),
+            }
+        }
+
+        if new_crosshairs {
+            self.crosshair_list
+                .set_loaded(self.model.crosshairs().len());
+        }
+
+        // New crosshairs sort anywhere into the list, so it's only rebuilt once the last one is in,
+        // which is also when weapons can be marked as missing their crosshair.
+        if !was_loaded && self.model.crosshairs_loaded() {
+            self.crosshair_list.render(&self.model);
+            self.weapon_list.refresh(&self.model);
+        }
+
+        self.app.redraw();
+    }
+
+    fn render_groups(&mut self) {
+        let current = self.group_input.choice();
+
+        self.group_input.clear();
+
+        for (name, _) in self.groups.iter() {
+            self.group_input.add_choice(name);
+        }
+
+        let i = current
+            .and_then(|c| self.groups.iter().position(|(name, _)| name == c))
+            .unwrap_or(0);
+        self.group_input.set_value(i as i32);
+
+        self.app.redraw();
+    }
+
+    /// The weapons of the group picked next to the `...to group` button.
+    fn selected_group(&self) -> Option<Vec<usize>> {
+        let name = self.group_input.choice()?;
+        let scripts = self.groups.get(&name)?;
+
+        Some(self.model.group_weapons(scripts))
+    }
+
+    /// Saves the weapons selected in the list as a group, named in a dialog.
+    fn save_group(&mut self) -> Result<()> {
+        let scripts = self
+            .model
+            .selected_weapons()
+            .into_iter()
+            .filter_map(|i| self.model.weapon(i))
+            .map(|w| w.file.name.clone())
+            .collect::<Vec<_>>();
+
+        if scripts.is_empty() {
+            bail!("No weapon selected");
+        }
+
+        let name = match dialog::input_default(
+            &format!(
+                "Name of the group of the {} selected weapon script(s):",
+                scripts.len()
+            ),
+            &self.group_input.choice().unwrap_or_default(),
+        ) {
+            Some(n) if !n.trim().is_empty() => n.trim().to_string(),
+            _ => return Ok(()),
+        };
+
+        if self.groups.get(&name).is_some() {
+            let confirmed = dialog::choice2_default(
+                &format!("Replace the weapons of group `{}`?", name),
+                "Cancel",
+                "Replace",
+                "",
+            ) == Some(1);
+
+            if !confirmed {
+                return Ok(());
+            }
+        }
+
+        self.groups.set(&name, scripts)?;
+        self.groups.save()?;
+
+        self.log.log(
+            LogType::Info,
+            format!("Saved group `{}` to `groups.json`", name),
+        );
+
+        self.render_groups();
+
+        if let Some(i) = self.groups.iter().position(|(n, _)| n == name) {
+            self.group_input.set_value(i as i32);
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the group picked next to the `...to group` button, after asking.
+    fn delete_group(&mut self) -> Result<()> {
+        let name = self
+            .group_input
+            .choice()
+            .ok_or_else(|| anyhow!("No group selected"))?;
+
+        let confirmed =
+            dialog::choice2_default(&format!("Delete group `{}`?", name), "Cancel", "Delete", "")
+                == Some(1);
+
+        if !confirmed {
+            return Ok(());
+        }
+
+        self.groups.remove(&name);
+        self.groups.save()?;
+
+        self.log
+            .log(LogType::Info, format!("Deleted group `{}`", name));
+
+        self.render_groups();
+
+        Ok(())
+    }
+
+    fn add_association(&mut self) -> Result<()> {
+        let scripts_dir = crate::scripts_dir()?;
+        let unknown = associations::unknown_scripts(&scripts_dir, &self.associations)?;
+
+        if unknown.is_empty() {
+            bail!("No unknown weapon scripts in `scripts` folder");
+        }
+
+        let (key, association) = match associate_dialog::show(&unknown) {
+            Some(a) => a,
+            None => return Ok(()),
+        };
+
+        Associations::save_user_entry(&key, association.clone())?;
+
+        let weapon = Weapon::load(&scripts_dir, &key, Some(association.clone()))?;
+
+        self.model.remove_unknown(&key);
+        self.model.push_weapon(weapon);
+        self.weapon_list.render(&self.model);
+
+        self.associations.insert(key.clone(), association);
+
+        self.log.log(
+            LogType::Info,
+            format!("Added {} to `associations.json`", key),
+        );
+
+        Ok(())
+    }
+
+    /// Shows the current weapon's crosshair the way its script draws it in game.
+    fn simulate(&mut self) -> Result<()> {
+        let (_, weapon) = self
+            .model
+            .current_weapon()
+            .ok_or_else(|| anyhow!("No weapon selected"))?;
+
+        let path = crate::materials_dir()?
+            .join(&weapon.file.crosshair)
+            .with_extension("vtf");
+
+        if !path.exists() {
+            bail!(
+                "{} uses `{}`, which isn't in the `materials` folder",
+                weapon.display(),
+                weapon.file.crosshair
+            );
+        }
+
+        let texture = texture::decode(&path)?;
+
+        simulate_dialog::show(
+            weapon.display(),
+            texture,
+            weapon.file.crosshair_rect,
+            &self.log,
+        );
+
+        Ok(())
+    }
+
+    fn export_contact_sheet(&mut self) -> Result<()> {
+        let (options, path) = match contact_sheet_dialog::show()? {
+            Some(o) => o,
+            None => return Ok(()),
+        };
+
+        for e in contact_sheet::export(&crate::crosshair_dir()?, &path, &options)? {
+            self.log.log(LogType::Error, e);
+        }
+
+        self.log
+            .log(LogType::Info, format!("Exported {}", path.display()));
+
+        Ok(())
+    }
+
+    fn export_loadout_card(&mut self) -> Result<()> {
+        let mut chooser = dialog::NativeFileChooser::new(dialog::FileDialogType::BrowseSaveFile);
+        chooser.set_filter("PNG\t*.png");
+        chooser.set_preset_file("loadout.png");
+        chooser.show();
+
+        let path = chooser.filename();
+
+        if path.as_os_str().is_empty() {
+            return Ok(());
+        }
+
+        let path = path.with_extension("png");
+
+        loadout_card::export(self.model.weapons(), &crate::materials_dir()?, &path)?;
+
+        self.log
+            .log(LogType::Info, format!("Exported {}", path.display()));
+
+        Ok(())
+    }
+
+    /// Saves a rule giving the selected crosshair, or the chosen explosion, to the weapons
+    /// matching conditions typed in, and applies it.
+    fn add_rule(&mut self) -> Result<()> {
+        let (crosshair, explosion) = if self.crosshair_radio.is_toggled() {
+            let crosshair = self
+                .model
+                .selected_crosshair()
+                .ok_or_else(|| anyhow!("No crosshair selected"))?;
+
+            (
+                Some(model::crosshair_stem(&crosshair.name).to_string()),
+                None,
+            )
+        } else {
+            let explosion = self
+                .explosion_input
+                .choice()
+                .ok_or_else(|| anyhow!("No explosion selected"))?;
+
+            (None, Some(ExplosionEffect::from(explosion.as_str())))
+        };
+
+        let when = match dialog::input_default(
+            "Weapons to apply to, like `ProjectileType is projectile_bullet`, \
+             `MeleeWeapon = 1` or `slot = 2 and class in [Scout, Engineer]`:",
+            "",
+        ) {
+            Some(w) if !w.trim().is_empty() => w,
+            _ => return Ok(()),
+        };
+
+        let rule = Rule::new(&when, crosshair, explosion)?;
+
+        let mut loadout = Loadout::load()?;
+        loadout.rules.push(rule.clone());
+        loadout.save()?;
+
+        self.log.log(
+            LogType::Info,
+            format!("Added rule {} to `{}`", rule, Loadout::path()?.display()),
+        );
+
+        self.apply_rules(&Loadout { rules: vec![rule] })
+    }
+
+    /// Applies every rule of the loadout next to the binary, after asking.
+    fn apply_loadout_rules(&mut self) -> Result<()> {
+        let loadout = Loadout::load()?;
+
+        if loadout.rules.is_empty() {
+            bail!("No rules yet; use `Weapons > Add rule...` to add some");
+        }
+
+        for (i, rule) in loadout.rules.iter().enumerate() {
+            self.log.log(LogType::Info, format!("{}. {}", i + 1, rule));
+        }
+
+        let changed = loadout.assignments(self.model.weapons()).len();
+
+        let confirmed = dialog::choice2_default(
+            &format!(
+                "Apply the {} rule(s) listed in the log, changing {} weapon script(s)?",
+                loadout.rules.len(),
+                changed
+            ),
+            "Cancel",
+            "Apply",
+            "",
+        ) == Some(1);
+
+        if !confirmed {
+            return Ok(());
+        }
+
+        self.apply_rules(&loadout)
+    }
+
+    fn apply_rules(&mut self, loadout: &Loadout) -> Result<()> {
+        let assignments = loadout.assignments(self.model.weapons());
+
+        if assignments.is_empty() {
+            self.log.log(LogType::Info, "No weapon scripts to change");
+            return Ok(());
+        }
+
+        let (changes, errors) =
+            rules::apply(self.model.weapons(), &assignments, &crate::crosshair_dir()?);
+
+        for c in changes {
+            self.log.log(LogType::Info, c);
+        }
+
+        for e in errors {
+            self.log.log(LogType::Error, e);
+        }
+
+        for a in &assignments {
+            error_log!(self.log, self.model.reload_weapon(a.weapon));
+            self.weapon_list.update_row(&self.model, a.weapon);
+        }
+
+        // Usage counts changed along with the scripts.
+        self.crosshair_list.render(&self.model);
+
+        Ok(())
+    }
+
+    /// Writes class configs setting `cl_crosshair_file` by slot into a chosen cfg folder.
+    fn export_class_cfgs(&mut self) -> Result<()> {
+        let root = crate::exe_dir()?;
+
+        let mut chooser = dialog::NativeFileChooser::new(dialog::FileDialogType::BrowseDir);
+        chooser.set_directory(&root.join("cfg"))?;
+        chooser.show();
+
+        let dir = chooser.filename();
+
+        if dir.as_os_str().is_empty() {
+            return Ok(());
+        }
+
+        let class_cfg::Written {
+            files: written,
+            skipped,
+        } = class_cfg::write(self.model.weapons(), &dir, &class_cfg::Options::default())?;
+
+        for path in &skipped {
+            self.log.log(
+                LogType::Error,
+                format!("Skipped {}, which isn't one of ours", path.display()),
+            );
+        }
+
+        for (path, created) in &written {
+            if path.starts_with(&root) {
+                manifest::record_write(path, *created)?;
+            }
+        }
+
+        self.log.log(
+            LogType::Info,
+            format!("Exported {} config(s) to {}", written.len(), dir.display()),
+        );
+
+        Ok(())
+    }
+
+    /// Lists the crosshairs no weapon uses in the log, then moves them into the `pruned` folder
+    /// if confirmed.
+    fn prune_crosshairs(&mut self) -> Result<()> {
+        let root = crate::exe_dir()?;
+
+        // Read from disk, so scripts that failed to load keep their crosshairs.
+        let (weapons, errors) = model::load_weapons(&crate::scripts_dir()?, &self.associations)?;
+        let plan = prune::plan_weapons(&root, &weapons, &errors)?;
+
+        if plan.prune.is_empty() {
+            self.log.log(LogType::Info, "No unused crosshairs to prune");
+            return Ok(());
+        }
+
+        for file in &plan.prune {
+            self.log
+                .log(LogType::Info, format!("Unused: {}", file.display()));
+        }
+
+        let confirmed = dialog::choice2_default(
+            &format!(
+                "Move the {} unused crosshair file(s) listed in the log into `{}`, keeping {}?\n\
+                 Crosshairs > Restore pruned crosshairs moves them back.",
+                plan.prune.len(),
+                prune::PRUNED_DIR,
+                plan.keep.len()
+            ),
+            "Cancel",
+            "Prune",
+            "",
+        ) == Some(1);
+
+        if !confirmed {
+            return Ok(());
+        }
+
+        let errors = prune::prune(&root, &plan)?;
+
+        self.log.log(
+            LogType::Info,
+            format!(
+                "Pruned {} file(s) into `{}`",
+                plan.prune.len() - errors.len(),
+                prune::PRUNED_DIR
+            ),
+        );
+
+        for e in errors {
+            self.log.log(LogType::Error, e);
+        }
+
+        self.reload_crosshairs();
+
+        Ok(())
+    }
+
+    fn restore_crosshairs(&mut self) -> Result<()> {
+        let (restored, errors) = prune::restore(&crate::exe_dir()?)?;
+
+        self.log.log(
+            LogType::Info,
+            format!("Restored {} file(s)", restored.len()),
+        );
+
+        for e in errors {
+            self.log.log(LogType::Error, e);
+        }
+
+        self.reload_crosshairs();
+
+        Ok(())
+    }
+
+    /// Logs the weapon scripts other mods in the custom folder also have, then merges our
+    /// crosshairs and explosions into the winning copies if confirmed.
+    fn check_overrides(&mut self) -> Result<()> {
+        let ours = crate::exe_dir()?;
+        let (overrides, errors) = overrides::scan(&ours)?;
+
+        for e in errors {
+            self.log.log(LogType::Error, e);
+        }
+
+        let mut lost = Vec::new();
+
+        for o in &overrides {
+            let winner = o.winner();
+
+            if winner == ours {
+                self.log.log(
+                    LogType::Info,
+                    format!("{} is also in other mods; ours wins", o.script),
+                );
+                continue;
+            }
+
+            self.log.log(
+                LogType::Info,
+                format!(
+                    "{} is overridden by {}",
+                    o.script,
+                    winner.file_name().unwrap().to_string_lossy()
+                ),
+            );
+
+            if o.winning_script().is_some() {
+                lost.push(o);
+            }
+        }
+
+        if overrides.is_empty() {
+            self.log
+                .log(LogType::Info, "No other mods override our weapon scripts");
+        }
+
+        if lost.is_empty() {
+            return Ok(());
+        }
+
+        let confirmed = dialog::choice2_default(
+            &format!(
+                "Copy our crosshairs and explosions into the {} overriding script(s) of other \
+                 mods listed in the log?",
+                lost.len()
+            ),
+            "Cancel",
+            "Merge",
+            "",
+        ) == Some(1);
+
+        if !confirmed {
+            return Ok(());
+        }
+
+        for o in lost {
+            match overrides::merge(&ours, o) {
+                Ok(missing) if missing.is_empty() => {
+                    self.log.log(LogType::Info, format!("Merged {}", o.script))
+                }
+                Ok(missing) => self.log.log(
+                    LogType::Info,
+                    format!("Merged {}, without {}", o.script, missing.join(", ")),
+                ),
+                Err(e) => self
+                    .log
+                    .log(LogType::Error, format!("Skipping {}; {:#}", o.script, e)),
+            }
         }
 
         Ok(())
     }
 
+    /// Reads the crosshair folder again after files were moved in or out of it.
+    fn reload_crosshairs(&mut self) {
+        self.crosshair_generation += 1;
+        self.model.clear_crosshairs();
+        self.crosshair_list.render(&self.model);
+        self.weapon_list.refresh(&self.model);
+
+        std::thread::spawn({
+            let mut log = self.log.clone();
+            let tx = self.loaded_tx.clone();
+            let s = self.s;
+            let generation = self.crosshair_generation;
+
+            move || error_log!(log, crosshair_list::load(&tx, s, generation))
+        });
+    }
+
     pub fn launch(&mut self) {
         std::thread::spawn({
-            let mut weapon_list = self.weapon_list.clone();
-            let mut crosshair_list = self.crosshair_list.clone();
             let mut log = self.log.clone();
+            let associations = self.associations.clone();
 
+            let tx = self.loaded_tx.clone();
             let s = self.s;
+            let generation = self.crosshair_generation;
 
             move || {
-                error_log!(log, weapon_list.init(&mut log, s));
-                error_log!(log, crosshair_list.init(&mut log, s));
+                error_log!(log, log_changes(&mut log));
+                error_log!(log, log_overrides(&mut log));
+                error_log!(log, weapon_list::load(&associations, &mut log, &tx, s));
+                error_log!(log, crosshair_list::load(&tx, s, generation));
             }
         });
 
         while self.app.wait() {
             if let Some(msg) = self.r.recv() {
                 match msg {
-                    Message::WeaponListClicked => match self.weapon_list.selected() {
-                        None => self.log.log(
-                            LogType::Error,
-                            format!(
-                                "Item index `{}` doesn't have any data",
-                                self.weapon_list.list.value()
-                            ),
-                        ),
-                        Some(wf) => {
-                            error_log!(self.log, self.display_info(&wf));
-                            error_log!(self.log, self.set_explosion_choice(&wf));
-                        }
-                    },
+                    Message::WeaponListClicked => self.weapon_clicked(),
+                    Message::WeaponFilterChanged => self.filter_weapons(),
+                    Message::CrosshairListClicked => self.crosshair_clicked(),
+                    Message::CrosshairFilterChanged => self.filter_crosshairs(),
+                    Message::OutlineToggled => self.toggle_outline(),
+                    Message::EditCrosshairDetails => {
+                        error_log!(self.log, self.edit_crosshair_details())
+                    }
+                    Message::ToggleFavourite => error_log!(self.log, self.toggle_favourite()),
                     Message::ButtonClicked(btn) => match btn {
                         ButtonMsg::Apply => {
                             if self.crosshair_radio.is_toggled() {
-                                let all_selected = self.weapon_list.all_selected();
+                                let all_selected = self.model.selected_weapons();
 
                                 error_log!(self.log, self.apply_crosshairs(all_selected));
                             } else {
-                                let weapon = match self.weapon_list.selected() {
-                                    Some(w) => w,
-                                    None => {
-                                        self.log.log(LogType::Error, "No selected item");
-                                        continue;
-                                    }
-                                };
-
-                                let i = self.weapon_list.list.value();
-
-                                if !USES_EXPLOSION.contains(&weapon.name.as_str()) {
-                                    self.log.log(
-                                        LogType::Error,
-                                        format!("{} doesn't use explosions", weapon.name),
-                                    );
-                                    continue;
-                                }
-
-                                error_log!(self.log, self.change_explosion(&weapon));
-                                error_log!(self.log, self.weapon_list.update_weapon(i, &weapon));
+                                error_log!(self.log, self.apply_explosion());
                             }
                         }
                         ButtonMsg::ToClass => {
-                            let selected = match self.weapon_list.selected() {
-                                Some(s) => s,
+                            let selected = match self.model.current_weapon() {
+                                Some((_, w)) => w.file.class,
                                 None => {
                                     self.log.log(LogType::Error, "No weapon selected");
                                     continue;
                                 }
                             };
 
-                            let all_class = self.weapon_list.all_class(&selected.class);
+                            let all_class = self.model.all_class(selected);
 
                             error_log!(self.log, self.apply_crosshairs(all_class));
                         }
                         ButtonMsg::ToSlot => {
-                            let selected = match self.weapon_list.selected() {
-                                Some(s) => s,
+                            let selected = match self.model.current_weapon() {
+                                Some((_, w)) => w.file.slot,
                                 None => {
                                     self.log.log(LogType::Error, "No weapon selected");
                                     continue;
                                 }
                             };
 
-                            let all_slot = self.weapon_list.all_slot(selected.slot);
+                            let all_slot = self.model.all_slot(selected);
 
                             error_log!(self.log, self.apply_crosshairs(all_slot));
                         }
                         ButtonMsg::ToAll => {
-                            let all_weapons = self.weapon_list.all_items();
+                            let all_weapons = self.model.all_weapons();
 
                             error_log!(self.log, self.apply_crosshairs(all_weapons));
                         }
+                        ButtonMsg::ToGroup => {
+                            let group = match self.selected_group() {
+                                Some(g) => g,
+                                None => {
+                                    self.log.log(LogType::Error, "No group selected");
+                                    continue;
+                                }
+                            };
+
+                            error_log!(self.log, self.apply_crosshairs(group));
+                        }
                     },
                     Message::CrosshairRadioClicked => {
-                        self.button_group.0.activate();
-                        self.button_group.1.activate();
-                        self.button_group.2.activate();
-
-                        self.weapon_list.crosshair_display(&USES_EXPLOSION);
+                        self.set_display_mode(DisplayMode::Crosshairs)
                     }
                     Message::ExplosionRadioClicked => {
-                        self.button_group.0.deactivate();
-                        self.button_group.1.deactivate();
-                        self.button_group.2.deactivate();
-
-                        self.weapon_list.explosion_display(&USES_EXPLOSION);
+                        self.set_display_mode(DisplayMode::Explosions)
+                    }
+                    Message::AddAssociation => error_log!(self.log, self.add_association()),
+                    Message::Simulate => error_log!(self.log, self.simulate()),
+                    Message::ExportContactSheet => {
+                        error_log!(self.log, self.export_contact_sheet())
                     }
-                    Message::Redraw => self.app.redraw(),
+                    Message::ExportLoadoutCard => error_log!(self.log, self.export_loadout_card()),
+                    Message::PruneCrosshairs => error_log!(self.log, self.prune_crosshairs()),
+                    Message::RestoreCrosshairs => error_log!(self.log, self.restore_crosshairs()),
+                    Message::CheckOverrides => error_log!(self.log, self.check_overrides()),
+                    Message::ExportClassCfgs => error_log!(self.log, self.export_class_cfgs()),
+                    Message::AddRule => error_log!(self.log, self.add_rule()),
+                    Message::ApplyRules => error_log!(self.log, self.apply_loadout_rules()),
+                    Message::SaveGroup => error_log!(self.log, self.save_group()),
+                    Message::DeleteGroup => error_log!(self.log, self.delete_group()),
+                    Message::PreviewChanged => self.preview.render(),
+                    Message::LoadScreenshot => error_log!(self.log, self.preview.load_screenshot()),
+                    Message::Loaded => self.receive_loaded(),
                 }
             }
         }
@@ -0,0 +1,142 @@
+use crate::gui::Message;
+use crate::model::CrosshairItem;
+use crate::preview::{self, Background};
+use crate::texture;
+
+use anyhow::{Context, Result};
+use fltk::{
+    app::Sender, button, dialog, enums, frame, group::Flex, image::RgbImage, menu, prelude::*,
+};
+
+const ZOOMS: [u32; 3] = [1, 2, 4];
+
+/// The selected crosshair at full size, to judge how visible it is before applying it.
+#[derive(Clone)]
+pub(crate) struct Preview {
+    frame: frame::Frame,
+    zoom: menu::Choice,
+    background: menu::Choice,
+    alpha: button::CheckButton,
+    crosshair: Option<image::RgbaImage>,
+    screenshot: Option<Background>,
+}
+
+impl Preview {
+    pub fn new(s: Sender<Message>) -> Self {
+        let mut col = Flex::default_fill().column();
+
+        let mut frame = frame::Frame::default_fill();
+        frame.set_frame(enums::FrameType::DownBox);
+
+        let mut row = Flex::default().row();
+        col.set_size(&row, 25);
+
+        let mut zoom = menu::Choice::default_fill();
+        for z in ZOOMS {
+            zoom.add_choice(&format!("{}x", z));
+        }
+        zoom.set_value(ZOOMS.len() as i32 - 1);
+        zoom.emit(s, Message::PreviewChanged);
+        row.set_size(&zoom, 50);
+
+        let mut background = menu::Choice::default_fill();
+        for name in Background::NAMES {
+            background.add_choice(name);
+        }
+        background.add_choice("Screenshot");
+        background.set_value(0);
+        background.emit(s, Message::PreviewChanged);
+
+        row.end();
+
+        let mut row = Flex::default().row();
+        col.set_size(&row, 25);
+
+        let mut alpha = button::CheckButton::default_fill().with_label("Alpha only");
+        alpha.emit(s, Message::PreviewChanged);
+
+        let mut load_btn = button::Button::default_fill().with_label("Screenshot...");
+        load_btn.emit(s, Message::LoadScreenshot);
+
+        row.end();
+
+        col.end();
+
+        Self {
+            frame,
+            zoom,
+            background,
+            alpha,
+            crosshair: None,
+            screenshot: None,
+        }
+    }
+
+    pub fn show(&mut self, crosshair: &CrosshairItem) -> Result<()> {
+        self.crosshair = None;
+        self.render();
+
+        self.crosshair = Some(texture::decode(&crosshair.path)?);
+        self.render();
+
+        Ok(())
+    }
+
+    /// Asks for a screenshot to use as background, then switches to it.
+    pub fn load_screenshot(&mut self) -> Result<()> {
+        let mut chooser = dialog::NativeFileChooser::new(dialog::FileDialogType::BrowseFile);
+        chooser.set_filter("Images\t*.{png,jpg,jpeg,tga,bmp}");
+        chooser.show();
+
+        let path = chooser.filename();
+
+        if path.as_os_str().is_empty() {
+            return Ok(());
+        }
+
+        let screenshot = image::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?
+            .to_rgba();
+
+        self.screenshot = Some(Background::Screenshot(screenshot));
+        self.background.set_value(Background::NAMES.len() as i32);
+        self.render();
+
+        Ok(())
+    }
+
+    pub fn render(&mut self) {
+        let crosshair = match &self.crosshair {
+            Some(c) => c,
+            None => {
+                self.frame.set_image(None::<RgbImage>);
+                self.frame.redraw();
+                return;
+            }
+        };
+
+        let zoom = ZOOMS[self.zoom.value().max(0) as usize];
+
+        let solid;
+        let background = match (self.background.value() as usize, &self.screenshot) {
+            (i, Some(screenshot)) if i == Background::NAMES.len() => screenshot,
+            (i, _) => {
+                solid = Background::from_index(i);
+                &solid
+            }
+        };
+
+        let preview = preview::render(crosshair, zoom, background, self.alpha.is_checked());
+
+        let image = RgbImage::new(
+            preview.as_raw(),
+            preview.width() as i32,
+            preview.height() as i32,
+            enums::ColorDepth::Rgba8,
+        )
+        .ok();
+
+        self.frame.set_image(image);
+        self.frame.redraw();
+    }
+}
@@ -0,0 +1,213 @@
+use crate::gui::{Log, LogType};
+use crate::preview::Background;
+use crate::simulate::{self, Settings, RESOLUTIONS};
+use crate::CrosshairRect;
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use fltk::{
+    app, button, dialog, enums, frame, group::Flex, image::RgbImage, input, menu, prelude::*,
+    window,
+};
+
+const PREVIEW_SIZE: (i32, i32) = (640, 360);
+
+struct State {
+    texture: image::RgbaImage,
+    rect: CrosshairRect,
+    settings: Settings,
+    background: Background,
+    screen: image::RgbaImage,
+}
+
+impl State {
+    fn render(&mut self, frame: &mut frame::Frame) {
+        self.screen = simulate::render(&self.texture, self.rect, &self.settings, &self.background);
+
+        let image = RgbImage::new(
+            self.screen.as_raw(),
+            self.screen.width() as i32,
+            self.screen.height() as i32,
+            enums::ColorDepth::Rgba8,
+        );
+
+        if let Ok(mut image) = image {
+            image.scale(PREVIEW_SIZE.0, PREVIEW_SIZE.1, true, true);
+            frame.set_image(Some(image));
+        }
+
+        let (width, height) = simulate::screen_size(self.rect, &self.settings);
+
+        frame.set_label(&format!(
+            "{}x{}, crosshair drawn at {}x{}",
+            self.settings.resolution.0, self.settings.resolution.1, width, height,
+        ));
+        frame.redraw();
+    }
+
+    fn export(&self, path: &Path) -> Result<()> {
+        self.screen
+            .save(path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Shows `texture` as `weapon`'s script draws it on a screen of a chosen resolution.
+pub fn show(weapon: &str, texture: image::RgbaImage, rect: CrosshairRect, log: &Log) {
+    let mut wind = window::Window::default()
+        .with_size(PREVIEW_SIZE.0 + 10, PREVIEW_SIZE.1 + 110)
+        .with_label(&format!("In-game preview: {}", weapon))
+        .center_screen();
+
+    let mut col = Flex::default_fill().column();
+    col.set_margin(5);
+
+    let mut screen =
+        frame::Frame::default_fill().with_align(enums::Align::Bottom | enums::Align::Inside);
+    screen.set_frame(enums::FrameType::DownBox);
+
+    let mut row = Flex::default().row();
+    col.set_size(&row, 30);
+
+    let mut resolution = menu::Choice::default_fill();
+    for (width, height) in RESOLUTIONS {
+        resolution.add_choice(&format!("{}x{}", width, height));
+    }
+    let default = Settings::default();
+    resolution.set_value(
+        RESOLUTIONS
+            .iter()
+            .position(|r| *r == default.resolution)
+            .unwrap_or(0) as i32,
+    );
+
+    let mut background = menu::Choice::default_fill();
+    for name in Background::NAMES {
+        background.add_choice(name);
+    }
+    background.set_value(0);
+
+    let label = frame::Frame::default().with_label("cl_crosshair_scale");
+    row.set_size(&label, 120);
+    let mut scale = input::FloatInput::default_fill();
+    scale.set_value(&default.scale.to_string());
+    scale.set_trigger(enums::CallbackTrigger::Changed);
+    row.set_size(&scale, 50);
+
+    let mut color_btn = button::Button::default_fill().with_label("Colour...");
+    row.set_size(&color_btn, 80);
+
+    row.end();
+
+    let mut row = Flex::default().row();
+    col.set_size(&row, 30);
+    frame::Frame::default_fill();
+    let mut export_btn = button::Button::default_fill().with_label("Export PNG...");
+    row.set_size(&export_btn, 120);
+    let mut close_btn = button::Button::default_fill().with_label("Close");
+    row.set_size(&close_btn, 100);
+    row.end();
+
+    col.end();
+    wind.end();
+
+    let state = Rc::new(RefCell::new(State {
+        texture,
+        rect,
+        settings: default,
+        background: Background::default(),
+        screen: image::RgbaImage::new(0, 0),
+    }));
+
+    state.borrow_mut().render(&mut screen);
+
+    resolution.set_callback({
+        let state = state.clone();
+        let mut screen = screen.clone();
+
+        move |r| {
+            let mut state = state.borrow_mut();
+            state.settings.resolution = RESOLUTIONS[r.value().max(0) as usize];
+            state.render(&mut screen);
+        }
+    });
+
+    background.set_callback({
+        let state = state.clone();
+        let mut screen = screen.clone();
+
+        move |b| {
+            let mut state = state.borrow_mut();
+            state.background = Background::from_index(b.value().max(0) as usize);
+            state.render(&mut screen);
+        }
+    });
+
+    scale.set_callback({
+        let state = state.clone();
+        let mut screen = screen.clone();
+
+        move |s| {
+            if let Ok(scale) = s.value().parse::<f32>() {
+                let mut state = state.borrow_mut();
+                state.settings.scale = scale.clamp(0.0, 1024.0);
+                state.render(&mut screen);
+            }
+        }
+    });
+
+    color_btn.set_callback({
+        let state = state.clone();
+        let mut screen = screen.clone();
+
+        move |_| {
+            if let Some((r, g, b)) =
+                dialog::color_chooser("Crosshair colour", dialog::ColorMode::Byte)
+            {
+                let mut state = state.borrow_mut();
+                state.settings.color = [r, g, b];
+                state.render(&mut screen);
+            }
+        }
+    });
+
+    export_btn.set_callback({
+        let state = state.clone();
+        let mut log = log.clone();
+
+        move |_| {
+            let mut chooser =
+                dialog::NativeFileChooser::new(dialog::FileDialogType::BrowseSaveFile);
+            chooser.set_filter("PNG\t*.png");
+            chooser.set_preset_file("crosshair.png");
+            chooser.show();
+
+            let path = chooser.filename();
+
+            if path.as_os_str().is_empty() {
+                return;
+            }
+
+            match state.borrow().export(&path.with_extension("png")) {
+                Ok(()) => log.log(LogType::Info, format!("Exported {}", path.display())),
+                Err(e) => log.log(LogType::Error, format!("{:#}", e)),
+            }
+        }
+    });
+
+    close_btn.set_callback({
+        let mut wind = wind.clone();
+
+        move |_| wind.hide()
+    });
+
+    wind.make_modal(true);
+    wind.show();
+
+    while wind.shown() {
+        app::wait();
+    }
+}
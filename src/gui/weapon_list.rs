@@ -1,208 +1,153 @@
-use crate::gui::{Log, LogType, Message};
-use crate::{WeaponFile, ASSOCIATIONS};
+use crate::associations::{Associations, Class, Slot};
+use crate::gui::{Loaded, Log, LogType, Message};
+use crate::localization::Localization;
+use crate::model::{self, Model, WeaponFilter};
 
-use std::path::Path;
+use std::sync::mpsc;
 
-use anyhow::{anyhow, bail, Context, Result};
-use fltk::app::Sender;
-use fltk::{app, browser, group, prelude::*};
+use anyhow::Result;
+use fltk::{app, browser, enums, group, input, menu, prelude::*};
 
 #[derive(Clone)]
 pub struct WeaponList {
-    pub list: browser::MultiBrowser,
-    json_data: json::JsonValue,
+    search: input::Input,
+    class: menu::Choice,
+    slot: menu::Choice,
+    list: browser::MultiBrowser,
 }
 
 impl WeaponList {
     pub fn new(s: app::Sender<Message>) -> Self {
-        let row = group::Flex::default_fill().row();
+        let mut col = group::Flex::default_fill().column();
+
+        let mut search = input::Input::default_fill();
+        search.set_tooltip("Class, category, script or item name");
+        search.set_trigger(enums::CallbackTrigger::Changed);
+        search.emit(s, Message::WeaponFilterChanged);
+        col.set_size(&search, 25);
+
+        let mut row = group::Flex::default().row();
+        col.set_size(&row, 25);
+
+        let mut class = menu::Choice::default_fill();
+        class.add_choice("All classes");
+        for c in Class::ALL {
+            class.add_choice(c.to_str());
+        }
+        class.set_value(0);
+        class.emit(s, Message::WeaponFilterChanged);
+
+        let mut slot = menu::Choice::default_fill();
+        slot.add_choice("All slots");
+        for sl in Slot::ALL {
+            slot.add_choice(sl.to_str());
+        }
+        slot.set_value(0);
+        slot.emit(s, Message::WeaponFilterChanged);
+
+        row.end();
 
         let mut list = browser::MultiBrowser::default_fill();
         list.emit(s, Message::WeaponListClicked);
         list.set_column_widths(&[85, 190]);
 
-        row.end();
+        col.end();
 
         Self {
+            search,
+            class,
+            slot,
             list,
-            json_data: json::parse(ASSOCIATIONS).expect("Failed to parse json"),
         }
     }
 
-    pub fn init(&mut self, log: &mut Log, s: Sender<Message>) -> Result<()> {
-        let mut scripts_dir =
-            std::env::current_exe().with_context(|| "Failed to get current executable path")?;
-        scripts_dir.pop();
-        scripts_dir.push("scripts");
-
-        if !scripts_dir.exists() {
-            bail!("Failed to find `scripts` folder");
+    /// The filter typed and picked above the list.
+    pub fn filter(&self) -> WeaponFilter {
+        WeaponFilter {
+            text: self.search.value(),
+            class: match self.class.value() {
+                i if i > 0 => Class::ALL.get(i as usize - 1).copied(),
+                _ => None,
+            },
+            slot: match self.slot.value() {
+                i if i > 0 => Slot::ALL.get(i as usize - 1).copied(),
+                _ => None,
+            },
         }
-
-        for weapon in self.json_data.entries() {
-            let weapon_file = match WeaponFile::new(
-                &scripts_dir.join(weapon.0).as_path().with_extension("txt"),
-                weapon.1["class"].to_string(),
-                weapon.1["slot"].as_u8().unwrap(),
-            ) {
-                Ok(wf) => wf,
-                Err(e) => {
-                    log.log(LogType::Error, format!("Skipping {}; {}", weapon.0, e));
-                    continue;
-                }
-            };
-
-            self.list.add_with_data(
-                &format!(
-                    "@f{}\t@f{}\t@f{}",
-                    weapon.1["class"],
-                    weapon.1["display"],
-                    &Path::new(&weapon_file.crosshair)
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                ),
-                weapon_file,
-            );
-        }
-
-        s.send(Message::Redraw);
-
-        Ok(())
     }
 
-    pub fn update_weapon(&mut self, i: i32, weapon: &WeaponFile) -> Result<()> {
-        let old = self.get_item(i).unwrap();
-        let new_weapon_file = WeaponFile::new(&weapon.path, old.class, old.slot)?;
-
-        let text = self.list.text(i).unwrap();
-
-        let mut text = text.split("@f");
-        text.next();
-
-        self.list.set_text(
-            i,
-            &format!(
-                "@f{}@f{}@f{}",
-                text.next().unwrap(),
-                text.next().unwrap(),
-                &Path::new(&new_weapon_file.crosshair)
-                    .file_name()
-                    .unwrap()
-                    .to_string_lossy()
-            ),
-        );
+    /// Rebuilds every row from the visible weapons, clearing the selection.
+    pub fn render(&mut self, model: &Model) {
+        self.list.clear();
 
-        self.list.set_data(i, new_weapon_file);
-
-        Ok(())
+        for i in model.visible_weapons() {
+            self.list.add(&model.weapon_row(*i));
+        }
     }
 
-    pub fn crosshair_display(&mut self, weapon_list: &[&str]) {
-        for (i, weapon) in self
-            .all_items()
-            .into_iter()
-            .filter(|w| weapon_list.contains(&w.1.name.as_str()))
-        {
-            let text = self.list.text(i).unwrap();
-
-            if !text.starts_with("@f@C4") {
-                return;
-            }
-
-            let text = text.replace("@C4", "");
-            let mut text = text.split("@f");
-            text.next();
-
-            self.list.set_text(
-                i,
-                &format!(
-                    "@f{}@f{}@f{}",
-                    text.next().unwrap(),
-                    text.next().unwrap(),
-                    &Path::new(&weapon.crosshair)
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                ),
-            );
+    /// Rewrites the text of every row, keeping the selection.
+    pub fn refresh(&mut self, model: &Model) {
+        for i in model.visible_weapons() {
+            self.update_row(model, *i);
         }
     }
 
-    pub fn explosion_display(&mut self, weapon_list: &[&str]) {
-        for (i, weapon) in self
-            .all_items()
-            .into_iter()
-            .filter(|w| weapon_list.contains(&w.1.name.as_str()))
-        {
-            let text = self.list.text(i).unwrap();
-
-            if text.starts_with("@f@C4") {
-                return;
-            }
-
-            let mut text = text.split("@f");
-            text.next();
-
-            self.list.set_text(
-                i,
-                &format!(
-                    "@f@C4{}@f@C4{}@f{}",
-                    text.next().unwrap(),
-                    text.next().unwrap(),
-                    &Path::new(&weapon.crosshair)
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                ),
-            );
+    pub fn update_row(&mut self, model: &Model, i: usize) {
+        if let Some(row) = model.weapon_list_row(i) {
+            self.list.set_text(row as i32 + 1, &model.weapon_row(i));
         }
     }
 
-    pub fn find_value(&self, key: &str) -> Result<(&str, &json::JsonValue)> {
-        self.json_data
-            .entries()
-            .find(|e| e.0 == key)
-            .ok_or_else(|| anyhow!("Key `{}` not in JSON data", key))
-    }
+    /// The row last clicked and every selected row, as model indices.
+    pub fn selection(&self, model: &Model) -> (Option<usize>, Vec<usize>) {
+        let weapon = |line: i32| model.visible_weapons().get(line as usize - 1).copied();
 
-    pub fn get_item(&self, i: i32) -> Option<WeaponFile> {
-        unsafe { self.list.data(i) }
-    }
+        let current = match self.list.value() {
+            0 => None,
+            line => weapon(line),
+        };
 
-    pub fn selected(&self) -> Option<WeaponFile> {
-        self.get_item(self.list.value())
-    }
+        let selected = (1..=self.list.size())
+            .filter(|line| self.list.selected(*line))
+            .filter_map(weapon)
+            .collect();
 
-    pub fn all_selected(&self) -> Vec<(i32, WeaponFile)> {
-        (1..=self.list.size())
-            .filter(|i| self.list.selected(*i))
-            .map(|i| (i, self.get_item(i)))
-            .filter(|(_, w)| w.is_some())
-            .map(|(i, w)| (i, w.unwrap()))
-            .collect()
+        (current, selected)
     }
+}
 
-    pub fn all_class(&self, class: &str) -> Vec<(i32, WeaponFile)> {
-        self.all_items()
-            .into_iter()
-            .filter(|w| w.1.class == class)
-            .collect()
+/// Reads every weapon script, with or without associations, off the main thread.
+pub fn load(
+    associations: &Associations,
+    log: &mut Log,
+    tx: &mpsc::Sender<Loaded>,
+    s: app::Sender<Message>,
+) -> Result<()> {
+    let (weapons, errors) = model::load_weapons(&crate::scripts_dir()?, associations)?;
+
+    for e in errors {
+        log.log(LogType::Error, e);
     }
 
-    pub fn all_slot(&self, slot: u8) -> Vec<(i32, WeaponFile)> {
-        self.all_items()
-            .into_iter()
-            .filter(|w| w.1.slot == slot)
-            .collect()
+    let unknown = weapons.iter().filter(|w| w.association.is_none()).count();
+
+    if unknown > 0 {
+        log.log(
+            LogType::Info,
+            format!(
+                "Found {} weapon script(s) with no associations, listed as `Unknown`",
+                unknown
+            ),
+        );
     }
 
-    pub fn all_items(&self) -> Vec<(i32, WeaponFile)> {
-        (1..=self.list.size())
-            .into_iter()
-            .map(|i| (i, self.get_item(i)))
-            .filter(|(_, w)| w.is_some())
-            .map(|(i, w)| (i, w.unwrap()))
-            .collect()
+    match Localization::load() {
+        Ok(localization) => tx.send(Loaded::Localization(localization))?,
+        Err(e) => tx.send(Loaded::Error(format!("{:#}", e)))?,
     }
+
+    tx.send(Loaded::Weapons(weapons))?;
+    s.send(Message::Loaded);
+
+    Ok(())
 }
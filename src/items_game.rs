@@ -0,0 +1,386 @@
+use crate::associations::{Association, Associations, Class, Slot};
+use crate::keyvalues::{KeyValues, Value};
+use crate::localization::Localization;
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+
+/// How deep prefabs can nest, so prefabs that use each other don't loop forever.
+const MAX_PREFAB_DEPTH: usize = 8;
+
+/// A weapon of `items_game.txt`, with its prefabs applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    pub name: String,
+    pub item_class: String,
+    /// The slot each class holds the item in.
+    pub classes: Vec<(Class, Slot)>,
+}
+
+/// A difference between two sets of associations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added(String, String),
+    Removed(String, String),
+    /// A weapon script that moved to another class or slot.
+    Moved(String, Class, Slot),
+}
+
+/// `key` of an item, or of the prefabs it's made from. Later prefabs win over earlier ones.
+fn lookup<'a>(
+    item: &'a KeyValues,
+    prefabs: Option<&'a KeyValues>,
+    key: &str,
+    depth: usize,
+) -> Option<&'a Value> {
+    if let Some(value) = item.get(key) {
+        return Some(value);
+    }
+
+    if depth >= MAX_PREFAB_DEPTH {
+        return None;
+    }
+
+    item.text("prefab")?
+        .split_whitespace()
+        .rev()
+        .filter_map(|p| prefabs?.block(p))
+        .find_map(|prefab| lookup(prefab, prefabs, key, depth + 1))
+}
+
+fn class(name: &str) -> Option<Class> {
+    Class::ALL
+        .into_iter()
+        .find(|c| *c != Class::All && c.to_str().eq_ignore_ascii_case(name))
+}
+
+/// The slot `class` holds an item of `item_slot` in, as the number key it's on. The spy's
+/// revolver and sapper are a slot ahead of other classes' secondaries and PDAs.
+fn slot(item_slot: &str, class: Class) -> Slot {
+    match (item_slot.to_ascii_lowercase().as_str(), class) {
+        ("secondary", Class::Spy) => Slot::Primary,
+        ("building", Class::Spy) => Slot::Secondary,
+        ("primary", _) => Slot::Primary,
+        ("secondary", _) => Slot::Secondary,
+        ("melee", _) => Slot::Melee,
+        ("pda" | "building", _) => Slot::Pda,
+        ("pda2", _) => Slot::Pda2,
+        _ => Slot::Other,
+    }
+}
+
+/// The weapon script `class` uses for items of `item_class`, which the game picks per class for
+/// weapons shared between classes.
+pub fn script_key(item_class: &str, class: Class) -> &str {
+    match (item_class, class) {
+        ("tf_weapon_shotgun", Class::Soldier) => "tf_weapon_shotgun_soldier",
+        ("tf_weapon_shotgun", Class::Pyro) => "tf_weapon_shotgun_pyro",
+        ("tf_weapon_shotgun", Class::Heavy) => "tf_weapon_shotgun_hwg",
+        ("tf_weapon_shotgun", Class::Engineer) => "tf_weapon_shotgun_primary",
+        ("tf_weapon_pistol", Class::Scout) => "tf_weapon_pistol_scout",
+        ("saxxy", Class::Scout) => "tf_weapon_bat",
+        ("saxxy", Class::Soldier) => "tf_weapon_shovel",
+        ("saxxy", Class::Pyro) => "tf_weapon_fireaxe",
+        ("saxxy", Class::Demoman) => "tf_weapon_bottle",
+        ("saxxy", Class::Heavy) => "tf_weapon_fists",
+        ("saxxy", Class::Engineer) => "tf_weapon_wrench",
+        ("saxxy", Class::Medic) => "tf_weapon_bonesaw",
+        ("saxxy", Class::Sniper) => "tf_weapon_club",
+        ("saxxy", Class::Spy) => "tf_weapon_knife",
+        _ => item_class,
+    }
+}
+
+/// The weapons of an `items_game.txt`, named in the language of `localization` where their
+/// `item_name` resolves.
+pub fn items(s: &str, localization: &Localization) -> Result<Vec<Item>> {
+    let kv = KeyValues::parse(s)?;
+
+    let items_game = match kv.block("items_game") {
+        Some(i) => i,
+        None => bail!("Expected an `items_game` block"),
+    };
+
+    let prefabs = items_game.block("prefabs");
+
+    let items = match items_game.block("items") {
+        Some(i) => i,
+        None => bail!("Missing `items` block"),
+    };
+
+    let mut weapons = Vec::new();
+
+    for (id, item) in &items.0 {
+        let item = match item {
+            Value::Block(b) if id != "default" => b,
+            _ => continue,
+        };
+
+        let text = |key: &str| match lookup(item, prefabs, key, 0) {
+            Some(Value::Text(s)) => Some(s.as_str()),
+            _ => None,
+        };
+
+        let item_class = match text("item_class") {
+            Some(c) if c.starts_with("tf_weapon_") || c == "saxxy" => c,
+            _ => continue,
+        };
+
+        let item_slot = text("item_slot").unwrap_or_default();
+
+        let name = text("item_name")
+            .and_then(|n| localization.name(n))
+            .or_else(|| text("name"))
+            .unwrap_or(id.as_str());
+
+        let classes = match lookup(item, prefabs, "used_by_classes", 0) {
+            Some(Value::Block(classes)) => classes
+                .texts()
+                .filter_map(|(name, value)| {
+                    let class = class(name)?;
+
+                    // Classes holding the item in another slot name it instead of `1`.
+                    let item_slot = if value == "1" { item_slot } else { value };
+
+                    Some((class, slot(item_slot, class)))
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        weapons.push(Item {
+            name: name.to_string(),
+            item_class: item_class.to_string(),
+            classes,
+        });
+    }
+
+    Ok(weapons)
+}
+
+/// Associations of the weapon scripts `items` use, limited to `scripts` when given. Scripts
+/// already in `current` keep their display name and order, and ones used by every class are
+/// associated with `All`.
+pub fn generate(
+    items: &[Item],
+    scripts: Option<&[String]>,
+    current: &Associations,
+) -> Associations {
+    // Items and the classes and slots they're used in, by weapon script.
+    let mut by_script = Vec::<(String, Vec<String>, Vec<(Class, Slot)>)>::new();
+
+    for item in items {
+        for &(class, slot) in &item.classes {
+            let key = script_key(&item.item_class, class);
+
+            if scripts.is_some_and(|s| !s.iter().any(|s| s == key)) {
+                continue;
+            }
+
+            let i = match by_script.iter().position(|e| e.0 == key) {
+                Some(i) => i,
+                None => {
+                    by_script.push((key.to_string(), Vec::new(), Vec::new()));
+                    by_script.len() - 1
+                }
+            };
+
+            let (_, names, uses) = &mut by_script[i];
+
+            if !names.contains(&item.name) {
+                names.push(item.name.clone());
+            }
+
+            uses.push((class, slot));
+        }
+    }
+
+    let mut generated = Associations::default();
+
+    let order = |key: &str| {
+        current
+            .iter()
+            .position(|(k, _)| k == key)
+            .unwrap_or(usize::MAX)
+    };
+    by_script.sort_by_key(|e| order(&e.0));
+
+    for (key, names, uses) in by_script {
+        let mut counts = HashMap::<Class, usize>::new();
+        for (class, _) in &uses {
+            *counts.entry(*class).or_default() += 1;
+        }
+
+        // The class with the most items, the first one winning ties.
+        let mut best = uses[0];
+        for &(class, slot) in &uses {
+            if counts[&class] > counts[&best.0] {
+                best = (class, slot);
+            }
+        }
+
+        let (class, slot) = best;
+
+        let class = if counts.len() == Class::ALL.len() - 1 {
+            Class::All
+        } else {
+            class
+        };
+
+        let display = current
+            .get(&key)
+            .map_or_else(|| names[0].clone(), |a| a.display.clone());
+
+        generated.insert(
+            key,
+            Association {
+                class,
+                slot,
+                display,
+                all: names,
+            },
+        );
+    }
+
+    generated
+}
+
+/// What changed from `old` to `new`, by weapon script.
+pub fn changes(old: &Associations, new: &Associations) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    for (key, association) in new.iter() {
+        let old = old.get(key);
+        let old_items = old.map_or(&[][..], |a| &a.all);
+
+        if let Some(old) = old {
+            if (old.class, old.slot) != (association.class, association.slot) {
+                changes.push(Change::Moved(
+                    key.to_string(),
+                    association.class,
+                    association.slot,
+                ));
+            }
+        }
+
+        for item in association.all.iter().filter(|i| !old_items.contains(i)) {
+            changes.push(Change::Added(key.to_string(), item.clone()));
+        }
+
+        for item in old_items.iter().filter(|i| !association.all.contains(i)) {
+            changes.push(Change::Removed(key.to_string(), item.clone()));
+        }
+    }
+
+    for (key, association) in old.iter().filter(|(k, _)| new.get(k).is_none()) {
+        for item in &association.all {
+            changes.push(Change::Removed(key.to_string(), item.clone()));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIXTURE: &str = "resources/fixtures/items_game.txt";
+
+    fn localization() -> Localization {
+        Localization::parse(
+            "\"lang\" { \"Tokens\" { \
+             \"TF_Weapon_Scattergun\" \"Scattergun\" \
+             \"TF_TheForceANature\" \"Force-A-Nature\" \
+             \"TF_Weapon_Shotgun\" \"Shotgun\" \
+             \"TF_Saxxy\" \"Saxxy\" } }",
+        )
+        .unwrap()
+    }
+
+    fn fixture_items() -> Vec<Item> {
+        let s = std::fs::read_to_string(FIXTURE).unwrap();
+        super::items(&s, &localization()).unwrap()
+    }
+
+    #[test]
+    fn items() {
+        let items = fixture_items();
+
+        // Everything but the default item and the hat.
+        assert_eq!(items.len(), 9);
+
+        let shotgun = items.iter().find(|i| i.name == "Shotgun").unwrap();
+        assert_eq!(
+            shotgun.classes,
+            [
+                (Class::Soldier, Slot::Secondary),
+                (Class::Pyro, Slot::Secondary),
+                (Class::Heavy, Slot::Secondary),
+                (Class::Engineer, Slot::Primary),
+            ]
+        );
+
+        // Prefabs after the first one still apply.
+        let fan = items.iter().find(|i| i.name == "Force-A-Nature").unwrap();
+        assert_eq!(fan.item_class, "tf_weapon_scattergun");
+
+        // Unresolved names fall back to the item's own.
+        assert!(items.iter().any(|i| i.name == "TF_WEAPON_BAT"));
+    }
+
+    #[test]
+    fn generate() {
+        let current = Associations::builtin().unwrap();
+        let scripts = [
+            "tf_weapon_bat",
+            "tf_weapon_scattergun",
+            "tf_weapon_shotgun_primary",
+            "tf_weapon_shotgun_soldier",
+            "tf_weapon_revolver",
+            "tf_weapon_knife",
+            "tf_weapon_grapplinghook",
+            "tf_weapon_hot_dogger",
+        ]
+        .map(String::from);
+
+        let generated = super::generate(&fixture_items(), Some(&scripts), &current);
+
+        let scattergun = generated.get("tf_weapon_scattergun").unwrap();
+        assert_eq!(scattergun.display, "Scatterguns");
+        assert_eq!(scattergun.all, ["Scattergun", "Force-A-Nature"]);
+
+        let engineer = generated.get("tf_weapon_shotgun_primary").unwrap();
+        assert_eq!(
+            (engineer.class, engineer.slot),
+            (Class::Engineer, Slot::Primary)
+        );
+
+        let revolver = generated.get("tf_weapon_revolver").unwrap();
+        assert_eq!((revolver.class, revolver.slot), (Class::Spy, Slot::Primary));
+
+        assert_eq!(generated.get("tf_weapon_knife").unwrap().all, ["Saxxy"]);
+        assert_eq!(
+            generated.get("tf_weapon_grapplinghook").unwrap().class,
+            Class::All
+        );
+        assert_eq!(
+            generated.get("tf_weapon_hot_dogger").unwrap().display,
+            "Hot Dogger"
+        );
+        assert!(generated.get("tf_weapon_shotgun_pyro").is_none());
+
+        let changes = super::changes(&current, &generated);
+
+        assert!(changes.contains(&Change::Added(
+            "tf_weapon_hot_dogger".into(),
+            "Hot Dogger".into()
+        )));
+        assert!(changes.contains(&Change::Removed(
+            "tf_weapon_scattergun".into(),
+            "The Back Scatter".into()
+        )));
+        assert!(changes.contains(&Change::Removed("tf_weapon_smg".into(), "SMG".into())));
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{bail, Result};
+
+/// A value of Valve's KeyValues format, used by weapon scripts, localisation files and
+/// `items_game.txt`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    Block(KeyValues),
+}
+
+/// Keys and values in file order. Keys can repeat and are looked up ignoring case, like the
+/// game does.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyValues(pub Vec<(String, Value)>);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    String(String),
+    /// Platform conditions like `[$WIN32]`, which are ignored.
+    Condition,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                let mut string = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => string.push('\n'),
+                            Some('t') => string.push('\t'),
+                            Some(c) => string.push(c),
+                            None => bail!("Unterminated string"),
+                        },
+                        Some(c) => string.push(c),
+                        None => bail!("Unterminated string"),
+                    }
+                }
+
+                tokens.push(Token::String(string));
+            }
+            c => {
+                let mut string = c.to_string();
+
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+
+                    string.push(c);
+                    chars.next();
+                }
+
+                if string.starts_with('[') && string.ends_with(']') {
+                    tokens.push(Token::Condition);
+                } else {
+                    tokens.push(Token::String(string));
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_block(
+    tokens: &mut std::iter::Peekable<std::vec::IntoIter<Token>>,
+    nested: bool,
+) -> Result<KeyValues> {
+    let mut block = KeyValues::default();
+
+    loop {
+        let key = match tokens.next() {
+            Some(Token::String(key)) => key,
+            Some(Token::Condition) => continue,
+            Some(Token::Close) if nested => return Ok(block),
+            Some(Token::Close) => bail!("Unexpected `}}`"),
+            Some(Token::Open) => bail!("Expected a key before `{{`"),
+            None if nested => bail!("Unexpected end of file; missing `}}`"),
+            None => return Ok(block),
+        };
+
+        if tokens.peek() == Some(&Token::Condition) {
+            tokens.next();
+        }
+
+        let value = match tokens.next() {
+            Some(Token::String(value)) => Value::Text(value),
+            Some(Token::Open) => Value::Block(parse_block(tokens, true)?),
+            _ => bail!("Missing value of `{}`", key),
+        };
+
+        block.0.push((key, value));
+    }
+}
+
+impl KeyValues {
+    pub fn parse(s: &str) -> Result<Self> {
+        parse_block(&mut tokenize(s)?.into_iter().peekable(), false)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn text(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Value::Text(s) => Some(s),
+            Value::Block(_) => None,
+        }
+    }
+
+    pub fn block(&self, key: &str) -> Option<&KeyValues> {
+        match self.get(key)? {
+            Value::Block(b) => Some(b),
+            Value::Text(_) => None,
+        }
+    }
+
+    /// The value at `path`, a list of keys into nested blocks.
+    pub fn path(&self, path: &[&str]) -> Option<&Value> {
+        let (last, blocks) = path.split_last()?;
+
+        blocks
+            .iter()
+            .try_fold(self, |block, key| block.block(key))?
+            .get(last)
+    }
+
+    /// Text values of the block, skipping nested blocks.
+    pub fn texts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().filter_map(|(k, v)| match v {
+            Value::Text(s) => Some((k.as_str(), s.as_str())),
+            Value::Block(_) => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let kv = KeyValues::parse(
+            "\
+// A comment
+WeaponData
+{
+\t\"printname\"\t\"PASS Time\" [$WIN32]
+\tBulletType unquoted
+\t\"escaped\" \"say \\\"hi\\\"\\n\"
+\tTextureData
+\t{
+\t\t\"crosshair\" { \"file\" \"sprites/crosshairs\" }
+\t}
+}",
+        )
+        .unwrap();
+
+        let data = kv.block("weapondata").unwrap();
+
+        assert_eq!(data.text("PrintName"), Some("PASS Time"));
+        assert_eq!(data.text("BulletType"), Some("unquoted"));
+        assert_eq!(data.text("escaped"), Some("say \"hi\"\n"));
+        assert_eq!(
+            kv.path(&["WeaponData", "TextureData", "crosshair", "file"]),
+            Some(&Value::Text("sprites/crosshairs".into()))
+        );
+        assert_eq!(data.texts().count(), 3);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(KeyValues::parse("a { b c").is_err());
+        assert!(KeyValues::parse("a }").is_err());
+        assert!(KeyValues::parse("\"a").is_err());
+        assert!(KeyValues::parse("a").is_err());
+    }
+}
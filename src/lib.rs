@@ -1,13 +1,36 @@
+pub mod associations;
+pub mod catalogue;
+pub mod class_cfg;
+pub mod cli;
+pub mod contact_sheet;
+pub mod font;
+pub mod groups;
 pub mod gui;
+pub mod items_game;
+pub mod keyvalues;
+pub mod loadout_card;
+pub mod localization;
+pub mod manifest;
+pub mod model;
+pub mod overrides;
+pub mod preview;
+pub mod prune;
+pub mod rebase;
+pub mod rules;
+pub mod simulate;
+pub mod texture;
+pub mod thumbnails;
+pub mod weapon_data;
 
 use std::borrow::Cow;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context, Result};
-use gui::crosshair_list::CrosshairItem;
-
-const ASSOCIATIONS: &str = include_str!("associations.json");
+use associations::{Class, Slot};
+use keyvalues::KeyValues;
+use model::CrosshairItem;
+use weapon_data::WeaponData;
 
 const USES_EXPLOSION: [&str; 7] = [
     "tf_weapon_rocketlauncher",
@@ -19,6 +42,44 @@ const USES_EXPLOSION: [&str; 7] = [
     "tf_weapon_pipebomblauncher",
 ];
 
+pub fn exe_dir() -> Result<PathBuf> {
+    let mut dir =
+        std::env::current_exe().with_context(|| "Failed to get current executable path")?;
+    dir.pop();
+
+    Ok(dir)
+}
+
+pub fn scripts_dir() -> Result<PathBuf> {
+    let dir = exe_dir()?.join("scripts");
+
+    if !dir.exists() {
+        bail!("Failed to find `scripts` folder");
+    }
+
+    Ok(dir)
+}
+
+pub fn materials_dir() -> Result<PathBuf> {
+    let dir = exe_dir()?.join("materials");
+
+    if !dir.exists() {
+        bail!("Failed to find `materials` folder");
+    }
+
+    Ok(dir)
+}
+
+pub fn crosshair_dir() -> Result<PathBuf> {
+    let dir = exe_dir()?.join("materials/vgui/replay/thumbnails");
+
+    if !dir.exists() {
+        bail!("Failed to find `materials/vgui/replay/thumbnails` folder");
+    }
+
+    Ok(dir)
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ExplosionEffect {
     Default,
@@ -66,18 +127,58 @@ impl From<&str> for ExplosionEffect {
     }
 }
 
+/// Part of the crosshair texture drawn by a weapon script, in texture pixels. The game draws it
+/// `width` by `height` screen pixels at the default `cl_crosshair_scale`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrosshairRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// The size most stock crosshairs are drawn at.
+impl Default for CrosshairRect {
+    fn default() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: 32,
+            height: 32,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct WeaponFile {
     name: String,
     path: PathBuf,
-    class: String,
-    slot: u8,
+    class: Option<Class>,
+    slot: Option<Slot>,
     crosshair: String,
+    crosshair_rect: CrosshairRect,
     explosion_effect: Option<ExplosionEffect>,
+    /// Name the game shows, usually a localisation token like `#TF_Weapon_SMG`.
+    printname: Option<String>,
+    /// Stats of the weapon, shown to help pick a crosshair.
+    data: WeaponData,
 }
 
 impl WeaponFile {
-    fn new(path: &Path, class: String, slot: u8) -> Result<Self> {
+    fn new(path: &Path, class: Class, slot: Slot) -> Result<Self> {
+        Self::open(path, Some(class), Some(slot))
+    }
+
+    /// A weapon script with no associations, so without a known class or slot.
+    fn unknown(path: &Path) -> Result<Self> {
+        Self::open(path, None, None)
+    }
+
+    fn reload(&self) -> Result<Self> {
+        Self::open(&self.path, self.class, self.slot)
+    }
+
+    fn open(path: &Path, class: Option<Class>, slot: Option<Slot>) -> Result<Self> {
         let file_name = match path.file_name() {
             Some(f) => f.to_str().unwrap(),
             None => bail!("Invalid file name `{}`", path.display()),
@@ -90,13 +191,25 @@ impl WeaponFile {
         let file_content =
             fs::read_to_string(path).with_context(|| format!("Failed to open {}", file_name))?;
 
+        // Scripts the game reads fine may still trip up the parser, which shouldn't stop their
+        // crosshairs from being edited.
+        let data = WeaponData::parse(&file_content).unwrap_or_default();
+
         let mut lines = file_content.lines().map(str::trim);
 
         let name = path.file_stem().unwrap().to_string_lossy().into_owned();
         let mut crosshair = String::new();
+        let mut crosshair_rect = CrosshairRect::default();
         let mut explosion_effect = None;
+        let mut printname = None;
 
         while let Some(line) = lines.next() {
+            if line.starts_with("\"printname\"") {
+                printname = KeyValues::parse(line)
+                    .ok()
+                    .and_then(|kv| kv.text("printname").map(str::to_string));
+            }
+
             if USES_EXPLOSION.contains(&name.as_str()) && line.starts_with("\"ExplosionEffect\"") {
                 explosion_effect = Some(
                     Self::get_value(line)
@@ -107,11 +220,33 @@ impl WeaponFile {
             if line.starts_with("\"crosshair\"") {
                 lines.next();
 
+                let default = CrosshairRect::default();
+
                 for line in lines.by_ref() {
+                    let value = || {
+                        Self::get_value(line)
+                            .with_context(|| format!("Failed to get value in {}", file_name))
+                    };
+                    // Sizes are only for previews, so odd ones shouldn't stop the script loading.
+                    let number = |default: i32| {
+                        Self::get_value(line)
+                            .ok()
+                            .and_then(|v| v.parse::<f32>().ok())
+                            .filter(|n| n.is_finite())
+                            .map_or(default, |n| n.round() as i32)
+                    };
+
                     if line.starts_with("\"file\"") {
-                        crosshair = Self::get_value(line)
-                            .with_context(|| format!("Failed to get value in {}", file_name))?
-                            .replace('"', "");
+                        crosshair = value()?.replace('"', "");
+                    } else if line.starts_with("\"x\"") {
+                        crosshair_rect.x = number(default.x);
+                    } else if line.starts_with("\"y\"") {
+                        crosshair_rect.y = number(default.y);
+                    } else if line.starts_with("\"width\"") {
+                        crosshair_rect.width = number(default.width);
+                    } else if line.starts_with("\"height\"") {
+                        crosshair_rect.height = number(default.height);
+                    } else if line.contains('}') {
                         break;
                     }
                 }
@@ -136,7 +271,10 @@ impl WeaponFile {
             class,
             slot,
             crosshair,
+            crosshair_rect,
             explosion_effect,
+            printname,
+            data,
         })
     }
 
@@ -273,12 +411,14 @@ mod test {
 
     #[test]
     fn associations() {
-        let json = json::parse(super::ASSOCIATIONS).unwrap();
+        let associations = associations::Associations::builtin().unwrap();
 
         for file in std::fs::read_dir(std::path::Path::new("resources/scripts")).unwrap() {
             let file = file.unwrap().path();
 
-            assert!(json.has_key(file.file_stem().unwrap().to_str().unwrap()));
+            assert!(associations
+                .get(file.file_stem().unwrap().to_str().unwrap())
+                .is_some());
         }
     }
 
@@ -286,19 +426,28 @@ mod test {
     fn parse() {
         let w = WeaponFile::new(
             Path::new("resources/scripts/tf_weapon_grenadelauncher.txt"),
-            "Demoman".into(),
-            1,
+            Class::Demoman,
+            Slot::Primary,
         )
         .unwrap();
 
         assert_eq!(w.name, "tf_weapon_grenadelauncher".to_string());
         assert_eq!(w.crosshair, "sprites/crosshairs".to_string());
+        assert_eq!(
+            w.crosshair_rect,
+            CrosshairRect {
+                x: 32,
+                y: 32,
+                width: 32,
+                height: 32
+            }
+        );
         assert_eq!(w.explosion_effect, Some(ExplosionEffect::Default));
 
         let w_2 = WeaponFile::new(
             Path::new("resources/scripts/tf_weapon_flaregun.txt"),
-            "Pyro".into(),
-            2,
+            Class::Pyro,
+            Slot::Secondary,
         )
         .unwrap();
 
@@ -311,8 +460,8 @@ mod test {
     fn replace_crosshair() {
         let w = WeaponFile::new(
             Path::new("resources/scripts/tf_weapon_grenadelauncher.txt"),
-            "Demoman".into(),
-            1,
+            Class::Demoman,
+            Slot::Primary,
         )
         .unwrap();
 
@@ -329,17 +478,18 @@ mod test {
 
         fs::write(&temp_dir, s).unwrap();
 
-        let w = WeaponFile::new(&temp_dir, "Demoman".into(), 1).unwrap();
+        let w = WeaponFile::new(&temp_dir, Class::Demoman, Slot::Primary).unwrap();
 
         assert_eq!(w.crosshair, "vgui/replay/thumbnails/bigcross");
+        assert_eq!((w.crosshair_rect.x, w.crosshair_rect.width), (0, 64));
     }
 
     #[test]
     fn replace_explosion() {
         let w = WeaponFile::new(
             Path::new("resources/scripts/tf_weapon_grenadelauncher.txt"),
-            "Demoman".into(),
-            1,
+            Class::Demoman,
+            Slot::Primary,
         )
         .unwrap();
 
@@ -352,8 +502,43 @@ mod test {
 
         fs::write(&temp_dir, s).unwrap();
 
-        let w = WeaponFile::new(&temp_dir, "Demoman".into(), 1).unwrap();
+        let w = WeaponFile::new(&temp_dir, Class::Demoman, Slot::Primary).unwrap();
 
         assert_eq!(w.explosion_effect, Some(ExplosionEffect::ElectricShock));
     }
+
+    #[test]
+    fn odd_crosshair_size() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("tf_weapon_odd.txt");
+
+        let s = fs::read_to_string("resources/scripts/tf_weapon_grenadelauncher.txt").unwrap();
+        let s = s
+            .replacen("\"width\"\t\t\"32\"", "\"width\"\t\t\"47.6\"", 1)
+            .replacen("\"height\"\t\"32\"", "\"height\"\t\"big\"", 1);
+        fs::write(&path, s).unwrap();
+
+        let w = WeaponFile::unknown(&path).unwrap();
+
+        assert_eq!(w.crosshair, "sprites/crosshairs".to_string());
+        // Fractions round, and anything else is the default size.
+        assert_eq!(
+            w.crosshair_rect,
+            CrosshairRect {
+                x: 32,
+                y: 32,
+                width: 48,
+                height: 32
+            }
+        );
+    }
+
+    #[test]
+    fn unknown() {
+        let w = WeaponFile::unknown(Path::new("resources/scripts/tf_weapon_flaregun.txt")).unwrap();
+
+        assert_eq!(w.class, None);
+        assert_eq!(w.slot, None);
+        assert_eq!(w.crosshair, "sprites/crosshairs".to_string());
+    }
 }
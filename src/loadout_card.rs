@@ -0,0 +1,259 @@
+use crate::associations::{Class, Slot};
+use crate::font;
+use crate::model::Weapon;
+use crate::preview;
+use crate::texture;
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, FilterType, Rgba, RgbaImage};
+
+const PADDING: u32 = 6;
+const THUMBNAIL_SIZE: u32 = 40;
+const ENTRY_HEIGHT: u32 = THUMBNAIL_SIZE + PADDING;
+const CLASS_WIDTH: u32 = 140;
+const SLOT_WIDTH: u32 = 300;
+const HEADER_HEIGHT: u32 = font::GLYPH_HEIGHT * 2 + 2 * PADDING;
+
+const BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+const GRID: Rgba<u8> = Rgba([72, 72, 72, 255]);
+const HEADER_COLOR: Rgba<u8> = Rgba([236, 174, 68, 255]);
+const NAME_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const DETAIL_COLOR: Rgba<u8> = Rgba([170, 170, 170, 255]);
+
+/// A weapon as shown on the card.
+pub struct Entry {
+    pub class: Class,
+    pub slot: Slot,
+    pub name: String,
+    /// Name of the crosshair, without its folder.
+    pub crosshair: String,
+    pub thumbnail: Option<RgbaImage>,
+    pub explosion: Option<String>,
+}
+
+/// The weapons with associations, with the crosshairs they use decoded from `materials_dir`.
+/// Crosshairs that aren't there, like the stock `sprites/crosshairs`, get no thumbnail.
+pub fn entries(weapons: &[Weapon], materials_dir: &Path) -> Vec<Entry> {
+    weapons
+        .iter()
+        .filter_map(|w| {
+            let association = w.association.as_ref()?;
+            let path = materials_dir.join(&w.file.crosshair).with_extension("vtf");
+
+            Some(Entry {
+                class: association.class,
+                slot: association.slot,
+                name: association.display.clone(),
+                crosshair: w.crosshair_file_name(),
+                thumbnail: texture::decode(&path).ok(),
+                explosion: w
+                    .file
+                    .explosion_effect
+                    .as_ref()
+                    .map(|e| e.to_str().to_string()),
+            })
+        })
+        .collect()
+}
+
+fn fill(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for py in y..(y + height).min(image.height()) {
+        for px in x..(x + width).min(image.width()) {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn draw_thumbnail(card: &mut RgbaImage, thumbnail: &RgbaImage, x: u32, y: u32) {
+    let scaled = DynamicImage::ImageRgba8(thumbnail.clone())
+        .resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle)
+        .to_rgba();
+
+    let x = x + (THUMBNAIL_SIZE - scaled.width()) / 2;
+    let y = y + (THUMBNAIL_SIZE - scaled.height()) / 2;
+
+    for (sx, sy, pixel) in scaled.enumerate_pixels() {
+        let below = card.get_pixel(x + sx, y + sy).0;
+        card.put_pixel(
+            x + sx,
+            y + sy,
+            preview::blend([below[0], below[1], below[2]], *pixel),
+        );
+    }
+}
+
+/// A class by slot grid of `entries`, leaving out the classes and slots with no weapons.
+pub fn render(entries: &[Entry]) -> RgbaImage {
+    let classes = Class::ALL
+        .into_iter()
+        .filter(|c| entries.iter().any(|e| e.class == *c))
+        .collect::<Vec<_>>();
+    let slots = Slot::ALL
+        .into_iter()
+        .filter(|s| entries.iter().any(|e| e.slot == *s))
+        .collect::<Vec<_>>();
+
+    let row_heights = classes
+        .iter()
+        .map(|c| {
+            let most = slots
+                .iter()
+                .map(|s| {
+                    entries
+                        .iter()
+                        .filter(|e| e.class == *c && e.slot == *s)
+                        .count() as u32
+                })
+                .max()
+                .unwrap_or(0);
+
+            most.max(1) * ENTRY_HEIGHT + PADDING
+        })
+        .collect::<Vec<_>>();
+
+    let width = CLASS_WIDTH + slots.len() as u32 * SLOT_WIDTH;
+    let height = HEADER_HEIGHT + row_heights.iter().sum::<u32>();
+
+    let mut card = RgbaImage::from_pixel(width, height, BACKGROUND);
+
+    for (i, slot) in slots.iter().enumerate() {
+        let x = CLASS_WIDTH + i as u32 * SLOT_WIDTH;
+
+        fill(&mut card, x, 0, 1, height, GRID);
+        font::draw_text(
+            &mut card,
+            (x + PADDING) as i64,
+            PADDING as i64,
+            slot.to_str(),
+            2,
+            HEADER_COLOR,
+        );
+    }
+
+    let mut y = HEADER_HEIGHT;
+
+    for (class, row_height) in classes.iter().zip(&row_heights) {
+        fill(&mut card, 0, y, width, 1, GRID);
+        font::draw_text(
+            &mut card,
+            PADDING as i64,
+            (y + PADDING) as i64,
+            class.to_str(),
+            2,
+            HEADER_COLOR,
+        );
+
+        for (i, slot) in slots.iter().enumerate() {
+            let x = CLASS_WIDTH + i as u32 * SLOT_WIDTH + PADDING;
+            let text_x = x + THUMBNAIL_SIZE + PADDING;
+            let text_width = SLOT_WIDTH - THUMBNAIL_SIZE - 3 * PADDING;
+
+            let in_cell = entries
+                .iter()
+                .filter(|e| e.class == *class && e.slot == *slot);
+
+            for (j, entry) in in_cell.enumerate() {
+                let top = y + PADDING + j as u32 * ENTRY_HEIGHT;
+
+                if let Some(thumbnail) = &entry.thumbnail {
+                    draw_thumbnail(&mut card, thumbnail, x, top);
+                }
+
+                font::draw_text(
+                    &mut card,
+                    text_x as i64,
+                    top as i64 + 4,
+                    &font::fit(&entry.name, text_width, 2),
+                    2,
+                    NAME_COLOR,
+                );
+
+                let detail = match &entry.explosion {
+                    Some(e) => format!("{}, explosion: {}", entry.crosshair, e),
+                    None => entry.crosshair.clone(),
+                };
+
+                font::draw_text(
+                    &mut card,
+                    text_x as i64,
+                    (top + 4 + font::GLYPH_HEIGHT * 2 + 6) as i64,
+                    &font::fit(&detail, text_width, 1),
+                    1,
+                    DETAIL_COLOR,
+                );
+            }
+        }
+
+        y += row_height;
+    }
+
+    card
+}
+
+/// Writes a loadout card of `weapons` to `path`.
+pub fn export(weapons: &[Weapon], materials_dir: &Path, path: &Path) -> Result<()> {
+    let entries = entries(weapons, materials_dir);
+
+    if entries.is_empty() {
+        bail!("No weapons with associations to export");
+    }
+
+    render(&entries)
+        .save(path)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(class: Class, slot: Slot) -> Entry {
+        Entry {
+            class,
+            slot,
+            name: "Rocket Launcher".into(),
+            crosshair: "dot".into(),
+            thumbnail: Some(RgbaImage::from_pixel(8, 8, Rgba([255, 0, 0, 255]))),
+            explosion: Some("Pyro Pool".into()),
+        }
+    }
+
+    #[test]
+    fn render() {
+        let card = super::render(&[
+            entry(Class::Soldier, Slot::Primary),
+            entry(Class::Soldier, Slot::Primary),
+            entry(Class::Scout, Slot::Melee),
+        ]);
+
+        // Two classes by two slots, the soldier row holding two weapons.
+        assert_eq!(
+            card.dimensions(),
+            (
+                CLASS_WIDTH + 2 * SLOT_WIDTH,
+                HEADER_HEIGHT + 3 * ENTRY_HEIGHT + 2 * PADDING
+            )
+        );
+
+        // The thumbnail of the first scout weapon, in the second column of the first row.
+        let (x, y) = (CLASS_WIDTH + SLOT_WIDTH + PADDING, HEADER_HEIGHT + PADDING);
+        assert_eq!(card.get_pixel(x + 1, y + 1).0, [255, 0, 0, 255]);
+        assert_eq!(card.get_pixel(x - 2, y + 1), &BACKGROUND);
+    }
+
+    #[test]
+    fn entries() {
+        let associations = crate::associations::Associations::builtin().unwrap();
+        let (weapons, _) =
+            crate::model::load_weapons(Path::new("resources/scripts"), &associations).unwrap();
+
+        let entries = super::entries(&weapons, Path::new("resources/materials"));
+
+        assert_eq!(entries.len(), weapons.len());
+        assert!(entries
+            .iter()
+            .any(|e| e.class == Class::Soldier && e.explosion.is_some()));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::keyvalues::{KeyValues, Value};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+/// Language the game falls back to, and the one every install has.
+const DEFAULT_LANGUAGE: &str = "english";
+
+/// Names of the game's localisation tokens in one language, from a `resource/tf_<language>.txt`.
+#[derive(Clone, Debug, Default)]
+pub struct Localization {
+    pub language: String,
+    /// Keyed on the lowercase token, without its `#`.
+    tokens: HashMap<String, String>,
+}
+
+/// Text of a localisation file, which the game ships as UTF-16 LE with a byte order mark.
+pub fn decode(data: &[u8]) -> Result<String> {
+    if let Some(data) = data.strip_prefix(&[0xff, 0xfe]) {
+        if data.len() % 2 != 0 {
+            bail!("Odd number of bytes in UTF-16 text");
+        }
+
+        let units = data
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect::<Vec<_>>();
+
+        return Ok(String::from_utf16(&units)?);
+    }
+
+    let data = data.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(data);
+
+    Ok(std::str::from_utf8(data)?.to_string())
+}
+
+/// The language TF2 runs in, from the Steam app manifest in `steamapps`.
+pub fn game_language(steamapps: &Path) -> Option<String> {
+    let s = fs::read_to_string(steamapps.join("appmanifest_440.acf")).ok()?;
+    let manifest = KeyValues::parse(&s).ok()?;
+
+    manifest
+        .path(&["AppState", "UserConfig", "language"])
+        .and_then(|v| match v {
+            Value::Text(s) if !s.is_empty() => Some(s.clone()),
+            _ => None,
+        })
+}
+
+impl Localization {
+    pub fn parse(s: &str) -> Result<Self> {
+        let kv = KeyValues::parse(s)?;
+
+        let lang = match kv.block("lang") {
+            Some(l) => l,
+            None => bail!("Expected a `lang` block"),
+        };
+
+        let tokens = match lang.block("Tokens") {
+            Some(t) => t,
+            None => bail!("Missing `Tokens` block"),
+        };
+
+        Ok(Self {
+            language: lang
+                .text("Language")
+                .unwrap_or_default()
+                .to_ascii_lowercase(),
+            tokens: tokens
+                .texts()
+                // Translations keep the English text under `[english]` keys.
+                .filter(|(k, _)| !k.starts_with('['))
+                .map(|(k, v)| (k.to_ascii_lowercase(), v.to_string()))
+                .collect(),
+        })
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = fs::read(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+        Self::parse(&decode(&data)?).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// The localisation of the game the binary's custom folder is in, in the language the game
+    /// runs in, or English. Empty if the game's files aren't there, so names fall back to
+    /// `associations.json`.
+    pub fn load() -> Result<Self> {
+        let root = crate::exe_dir()?;
+
+        // `tf/custom/<folder>`, in `steamapps/common/Team Fortress 2`.
+        let tf_dir = match root.parent().and_then(Path::parent) {
+            Some(d) => d,
+            None => return Ok(Self::default()),
+        };
+
+        let language = tf_dir
+            .ancestors()
+            .nth(3)
+            .and_then(game_language)
+            .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+
+        for language in [language.as_str(), DEFAULT_LANGUAGE] {
+            let path = tf_dir.join("resource").join(format!("tf_{}.txt", language));
+
+            if path.exists() {
+                return Self::open(&path);
+            }
+        }
+
+        Ok(Self::default())
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Text of `token`, with or without its `#`.
+    pub fn get(&self, token: &str) -> Option<&str> {
+        let token = token.strip_prefix('#').unwrap_or(token);
+
+        self.tokens
+            .get(&token.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// The name a weapon script's `printname` shows as, which is either a token or the name
+    /// itself.
+    pub fn name<'a>(&'a self, printname: &'a str) -> Option<&'a str> {
+        if printname.starts_with('#') {
+            self.get(printname)
+        } else {
+            Some(printname)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GERMAN: &str = "\
+\"lang\"
+{
+\"Language\" \"German\"
+\"Tokens\"
+{
+\"TF_Weapon_SMG\"\t\"Maschinenpistole\"
+\"[english]TF_Weapon_SMG\"\t\"SMG\"
+\"TF_Weapon_Bat\"\t\"Schläger\"
+}
+}";
+
+    fn utf16(s: &str) -> Vec<u8> {
+        let mut data = vec![0xff, 0xfe];
+        data.extend(s.encode_utf16().flat_map(u16::to_le_bytes));
+        data
+    }
+
+    #[test]
+    fn parse() {
+        let localization = Localization::parse(&decode(&utf16(GERMAN)).unwrap()).unwrap();
+
+        assert_eq!(localization.language, "german");
+        assert_eq!(localization.len(), 2);
+        assert_eq!(localization.get("#tf_weapon_smg"), Some("Maschinenpistole"));
+        assert_eq!(localization.name("#TF_Weapon_Bat"), Some("Schläger"));
+        assert_eq!(localization.name("PASS Time"), Some("PASS Time"));
+        assert_eq!(localization.name("#TF_Weapon_Minigun"), None);
+    }
+
+    #[test]
+    fn game_language() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(super::game_language(temp_dir.path()), None);
+
+        fs::write(
+            temp_dir.path().join("appmanifest_440.acf"),
+            "\"AppState\"\n{\n\t\"appid\"\t\"440\"\n\t\"UserConfig\"\n\t{\n\t\t\"language\"\t\"german\"\n\t}\n}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            super::game_language(temp_dir.path()).as_deref(),
+            Some("german")
+        );
+    }
+}
@@ -1,6 +1,38 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if !args.is_empty() {
+        attach_console();
+
+        if let Err(e) = crosshair_switcher::cli::run(&args) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
     let mut app = crosshair_switcher::gui::App::new("crosshair-switcher");
     app.launch();
 }
+
+/// Release builds have no console of their own, so tasks print to the one they were run from.
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Fails when there's no parent console, or already one from a debug build, which is fine.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
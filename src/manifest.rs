@@ -0,0 +1,396 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use sha2::{Digest, Sha256};
+
+/// Name of the manifest kept next to the binary.
+pub const FILE: &str = "install.json";
+/// Folders of a custom folder the tool installs.
+const INSTALLED_DIRS: [&str; 2] = ["scripts", "materials"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub sha256: String,
+    /// Whether the tool created the file, rather than changing one that was already there.
+    pub created: bool,
+}
+
+/// A file that no longer matches what the tool last wrote.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Modified(String),
+    Missing(String),
+}
+
+/// The files the tool installed or changed in a custom folder, with their hashes when it last
+/// wrote them, keyed on their path relative to the folder.
+#[derive(Clone, Debug, Default)]
+pub struct Manifest {
+    /// Version of the tool that last wrote the manifest.
+    pub version: String,
+    /// Where the installed files came from, like `dist` for a release.
+    pub source: String,
+    files: Vec<(String, Entry)>,
+}
+
+/// Records `file` in the manifest next to the binary after the tool wrote it, `created` being
+/// whether it didn't exist before. User config like `groups.json` isn't recorded, so uninstalling
+/// keeps it.
+pub fn record_write(file: &Path, created: bool) -> Result<()> {
+    let root = crate::exe_dir()?;
+
+    let mut manifest = Manifest::load(&root)?;
+    manifest.record(&root, file, created)?;
+    manifest.save(&root)
+}
+
+pub fn sha256(path: &Path) -> Result<String> {
+    let data = fs::read(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    Ok(format!("{:x}", Sha256::digest(&data)))
+}
+
+/// `file` relative to `root` with forward slashes, so manifests work across platforms.
+fn key(root: &Path, file: &Path) -> String {
+    file.strip_prefix(root)
+        .unwrap_or(file)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for file in dir
+        .read_dir()
+        .with_context(|| format!("Failed to read folder `{}`", dir.display()))?
+    {
+        let path = file?.path();
+
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the folders holding `file` that are left empty, up to `root`.
+pub(crate) fn remove_empty_parents(root: &Path, file: &Path) {
+    let mut dir = file.parent();
+
+    // Folders that still hold anything fail to be removed, which ends it.
+    while let Some(d) = dir.filter(|d| *d != root) {
+        if fs::remove_dir(d).is_err() {
+            break;
+        }
+
+        dir = d.parent();
+    }
+}
+
+impl Manifest {
+    pub fn path(root: &Path) -> PathBuf {
+        root.join(FILE)
+    }
+
+    /// The manifest of `root`, empty if the tool hasn't written one yet.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = Self::path(root);
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let s = fs::read_to_string(&path).with_context(|| format!("Failed to open {}", FILE))?;
+
+        Self::parse(&s).with_context(|| format!("Failed to parse {}", FILE))
+    }
+
+    /// Writes the manifest to `root` as written by this version, or removes it once it's empty.
+    pub fn save(&mut self, root: &Path) -> Result<()> {
+        let path = Self::path(root);
+
+        if self.files.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+
+            return Ok(());
+        }
+
+        self.version = env!("CARGO_PKG_VERSION").to_string();
+
+        fs::write(&path, self.to_json())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Every file in the `scripts` and `materials` folders of `root`, as installed from
+    /// `source`.
+    pub fn scan(root: &Path, source: &str) -> Result<Self> {
+        let mut paths = Vec::new();
+
+        for dir in INSTALLED_DIRS {
+            let dir = root.join(dir);
+
+            if dir.exists() {
+                walk(&dir, &mut paths)?;
+            }
+        }
+
+        paths.sort();
+
+        let mut manifest = Self {
+            source: source.to_string(),
+            ..Default::default()
+        };
+
+        for path in paths {
+            manifest.record(root, &path, true)?;
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        let json = json::parse(s)?;
+
+        if !json.is_object() || !json["files"].is_object() {
+            bail!("Expected an object with `files`");
+        }
+
+        let files = json["files"]
+            .entries()
+            .map(|(key, value)| {
+                let sha256 = value["sha256"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Missing or invalid `sha256` of `{}`", key))?;
+
+                Ok((
+                    key.to_string(),
+                    Entry {
+                        sha256: sha256.to_string(),
+                        created: value["created"].as_bool().unwrap_or(false),
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            version: json["version"].as_str().unwrap_or_default().to_string(),
+            source: json["source"].as_str().unwrap_or_default().to_string(),
+            files,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut files = json::JsonValue::new_object();
+
+        for (key, entry) in &self.files {
+            let mut value = json::JsonValue::new_object();
+            value["sha256"] = entry.sha256.as_str().into();
+            value["created"] = entry.created.into();
+
+            files[key.as_str()] = value;
+        }
+
+        let mut json = json::JsonValue::new_object();
+        json["version"] = self.version.as_str().into();
+        json["source"] = self.source.as_str().into();
+        json["files"] = files;
+
+        json::stringify_pretty(json, 4)
+    }
+
+    pub fn get(&self, root: &Path, file: &Path) -> Option<&Entry> {
+        let key = key(root, file);
+        self.files.iter().find(|f| f.0 == key).map(|f| &f.1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Stores the hash of `file` after the tool wrote it. Files already in the manifest keep
+    /// whether the tool created them.
+    pub fn record(&mut self, root: &Path, file: &Path, created: bool) -> Result<()> {
+        let key = key(root, file);
+        let sha256 = sha256(&root.join(&key))?;
+
+        match self.files.iter_mut().find(|f| f.0 == key) {
+            Some((_, entry)) => entry.sha256 = sha256,
+            None => self.files.push((key, Entry { sha256, created })),
+        }
+
+        Ok(())
+    }
+
+    /// Follows a file the tool moved.
+    pub fn rename(&mut self, root: &Path, from: &Path, to: &Path) {
+        let from = key(root, from);
+
+        if let Some(file) = self.files.iter_mut().find(|f| f.0 == from) {
+            file.0 = key(root, to);
+        }
+    }
+
+    /// Forgets a file the tool removed.
+    pub fn remove(&mut self, root: &Path, file: &Path) {
+        let key = key(root, file);
+        self.files.retain(|f| f.0 != key);
+    }
+
+    /// The files changed or removed by something other than the tool since it last wrote them.
+    pub fn changes(&self, root: &Path) -> Vec<Change> {
+        self.files
+            .iter()
+            .filter_map(|(key, entry)| {
+                let path = root.join(key);
+
+                if !path.exists() {
+                    Some(Change::Missing(key.clone()))
+                } else if sha256(&path).ok().as_ref() != Some(&entry.sha256) {
+                    Some(Change::Modified(key.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Removes every file the tool created in `root` and the folders left empty, keeping files
+    /// edited since. Files that aren't removed stay in the manifest. Returns the files removed
+    /// and why others were kept.
+    pub fn uninstall(&mut self, root: &Path) -> Result<(Vec<String>, Vec<String>)> {
+        let mut removed = Vec::new();
+        let mut errors = Vec::new();
+        let mut kept = Vec::new();
+
+        for (key, entry) in std::mem::take(&mut self.files) {
+            let path = root.join(&key);
+
+            if !entry.created || !path.exists() {
+                kept.push((key, entry));
+                continue;
+            }
+
+            let result = sha256(&path).and_then(|sha256| {
+                if sha256 != entry.sha256 {
+                    bail!("it was edited since it was installed");
+                }
+
+                fs::remove_file(&path).context("failed to remove it")
+            });
+
+            match result {
+                Ok(()) => {
+                    remove_empty_parents(root, &path);
+                    removed.push(key);
+                }
+                Err(e) => {
+                    errors.push(format!("Skipping {}; {:#}", key, e));
+                    kept.push((key, entry));
+                }
+            }
+        }
+
+        self.files = kept;
+        self.save(root)?;
+
+        Ok((removed, errors))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn custom_folder() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+
+        fs::create_dir_all(root.join("scripts")).unwrap();
+        fs::create_dir_all(root.join("materials/vgui/replay/thumbnails")).unwrap();
+
+        fs::write(root.join("scripts/tf_weapon_bat.txt"), "bat").unwrap();
+        fs::write(root.join("materials/vgui/replay/thumbnails/dot.vtf"), "dot").unwrap();
+
+        temp_dir
+    }
+
+    #[test]
+    fn changes() {
+        let temp_dir = custom_folder();
+        let root = temp_dir.path();
+
+        let mut manifest = Manifest::scan(root, "dist").unwrap();
+        assert_eq!(manifest.len(), 2);
+        assert_eq!(
+            manifest
+                .get(root, &root.join("scripts/tf_weapon_bat.txt"))
+                .unwrap()
+                .sha256,
+            // SHA-256 of `bat`.
+            "ca5bcec12f716f44d9745d349cc80422f0d14cbab09329caf533bef7c2d952eb"
+        );
+
+        manifest.save(root).unwrap();
+
+        let manifest = Manifest::load(root).unwrap();
+        assert_eq!(manifest.source, "dist");
+        assert_eq!(manifest.version, env!("CARGO_PKG_VERSION"));
+        assert!(manifest.changes(root).is_empty());
+
+        fs::write(root.join("scripts/tf_weapon_bat.txt"), "edited").unwrap();
+        fs::remove_file(root.join("materials/vgui/replay/thumbnails/dot.vtf")).unwrap();
+
+        assert_eq!(
+            manifest.changes(root),
+            [
+                Change::Missing("materials/vgui/replay/thumbnails/dot.vtf".into()),
+                Change::Modified("scripts/tf_weapon_bat.txt".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn uninstall() {
+        let temp_dir = custom_folder();
+        let root = temp_dir.path();
+
+        let mut manifest = Manifest::scan(root, "dist").unwrap();
+
+        // A file the user had, then changed by the tool, isn't removed.
+        fs::write(root.join("scripts/tf_weapon_user.txt"), "user").unwrap();
+        manifest
+            .record(root, &root.join("scripts/tf_weapon_user.txt"), false)
+            .unwrap();
+
+        // Neither is a file the user edited afterwards.
+        fs::write(root.join("scripts/tf_weapon_bat.txt"), "edited").unwrap();
+
+        let (removed, errors) = manifest.uninstall(root).unwrap();
+
+        assert_eq!(removed, ["materials/vgui/replay/thumbnails/dot.vtf"]);
+        assert_eq!(
+            errors,
+            ["Skipping scripts/tf_weapon_bat.txt; it was edited since it was installed"]
+        );
+        assert!(!root.join("materials").exists());
+        assert!(root.join("scripts/tf_weapon_user.txt").exists());
+        assert!(root.join("scripts/tf_weapon_bat.txt").exists());
+
+        // Everything not removed is still tracked.
+        let manifest = Manifest::load(root).unwrap();
+        assert_eq!(manifest.len(), 2);
+        assert!(manifest
+            .get(root, &root.join("scripts/tf_weapon_user.txt"))
+            .is_some());
+    }
+}
@@ -0,0 +1,1060 @@
+use crate::associations::{self, Association, Associations, Class, Slot};
+use crate::catalogue::{Catalogue, CrosshairMeta};
+use crate::localization::Localization;
+use crate::{WeaponFile, USES_EXPLOSION};
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+#[derive(Clone)]
+pub struct CrosshairItem {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: (i32, i32),
+}
+
+#[derive(Clone)]
+pub struct Weapon {
+    pub file: WeaponFile,
+    pub association: Option<Association>,
+}
+
+impl Weapon {
+    pub fn load(scripts_dir: &Path, key: &str, association: Option<Association>) -> Result<Self> {
+        let path = scripts_dir.join(key).with_extension("txt");
+
+        let file = match &association {
+            Some(a) => WeaponFile::new(&path, a.class, a.slot)?,
+            None => WeaponFile::unknown(&path)?,
+        };
+
+        Ok(Self { file, association })
+    }
+
+    pub fn class_label(&self) -> &str {
+        self.association
+            .as_ref()
+            .map_or("Unknown", |a| a.class.to_str())
+    }
+
+    pub fn display(&self) -> &str {
+        self.association
+            .as_ref()
+            .map_or(self.file.name.as_str(), |a| a.display.as_str())
+    }
+
+    /// The name the game shows for the weapon in the language of `localization`, or the one in
+    /// `associations.json` if its `printname` doesn't resolve.
+    pub fn name<'a>(&'a self, localization: &'a Localization) -> &'a str {
+        self.file
+            .printname
+            .as_deref()
+            .and_then(|p| localization.name(p))
+            .unwrap_or_else(|| self.display())
+    }
+
+    pub fn crosshair_file_name(&self) -> String {
+        Path::new(&self.file.crosshair)
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn uses_explosion(&self) -> bool {
+        USES_EXPLOSION.contains(&self.file.name.as_str())
+    }
+}
+
+/// Reads every associated weapon script in `scripts_dir`, followed by the scripts with no
+/// associations. Scripts that fail to parse are returned as errors instead.
+pub fn load_weapons(
+    scripts_dir: &Path,
+    associations: &Associations,
+) -> Result<(Vec<Weapon>, Vec<String>)> {
+    let mut weapons = Vec::new();
+    let mut errors = Vec::new();
+
+    let known = associations
+        .iter()
+        .map(|(k, a)| (k.to_string(), Some(a.clone())));
+    let unknown = associations::unknown_scripts(scripts_dir, associations)?
+        .into_iter()
+        .map(|k| (k, None));
+
+    for (key, association) in known.chain(unknown) {
+        match Weapon::load(scripts_dir, &key, association) {
+            Ok(w) => weapons.push(w),
+            Err(e) => errors.push(format!("Skipping {}; {}", key, e)),
+        }
+    }
+
+    Ok((weapons, errors))
+}
+
+/// What narrows down the weapon list. Text matches the class, category, script name and items
+/// of a weapon, ignoring case.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WeaponFilter {
+    pub text: String,
+    pub class: Option<Class>,
+    pub slot: Option<Slot>,
+}
+
+impl WeaponFilter {
+    pub fn matches(&self, weapon: &Weapon) -> bool {
+        if self.class.is_some() && weapon.file.class != self.class {
+            return false;
+        }
+
+        if self.slot.is_some() && weapon.file.slot != self.slot {
+            return false;
+        }
+
+        let text = self.text.trim().to_lowercase();
+
+        if text.is_empty() {
+            return true;
+        }
+
+        let items = weapon.association.iter().flat_map(|a| a.all.iter());
+
+        [weapon.class_label(), weapon.display(), &weapon.file.name]
+            .into_iter()
+            .chain(items.map(String::as_str))
+            .any(|s| s.to_lowercase().contains(&text))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrosshairFilter {
+    pub text: String,
+    /// Whether crosshairs are grouped by family.
+    pub group: bool,
+    pub favourites: bool,
+    /// A source or tag from the catalogue.
+    pub label: Option<String>,
+}
+
+impl CrosshairFilter {
+    pub fn matches(&self, name: &str, meta: Option<&CrosshairMeta>) -> bool {
+        if self.favourites && !meta.is_some_and(|m| m.favourite) {
+            return false;
+        }
+
+        if let Some(label) = &self.label {
+            if !meta.is_some_and(|m| m.has_label(label)) {
+                return false;
+            }
+        }
+
+        let text = self.text.trim().to_lowercase();
+
+        crosshair_stem(name).to_lowercase().contains(&text)
+            || meta.is_some_and(|m| m.contains(&text))
+    }
+}
+
+/// Compares names ignoring case, with runs of digits compared as numbers so `ql_2` comes before
+/// `ql_10`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(c);
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+
+                let (x, y) = (number(&mut a), number(&mut b));
+
+                match x.len().cmp(&y.len()).then_with(|| x.cmp(&y)) {
+                    Ordering::Equal => continue,
+                    o => return o,
+                }
+            }
+            (Some(x), Some(y)) => match x.to_ascii_lowercase().cmp(&y.to_ascii_lowercase()) {
+                Ordering::Equal => {
+                    a.next();
+                    b.next();
+                }
+                o => return o,
+            },
+        }
+    }
+}
+
+/// Name of a crosshair without its extension.
+pub fn crosshair_stem(name: &str) -> &str {
+    name.strip_suffix(".vtf").unwrap_or(name)
+}
+
+/// The material a weapon script refers to crosshair `name` by, like
+/// `vgui/replay/thumbnails/dot`, in lowercase as the game doesn't care about case.
+pub fn crosshair_material(name: &str) -> String {
+    material_key(&format!("vgui/replay/thumbnails/{}", crosshair_stem(name)))
+}
+
+/// `material` the way the game looks it up, so paths written differently compare equal.
+pub fn material_key(material: &str) -> String {
+    material
+        .trim()
+        .replace('\\', "/")
+        .trim_end_matches(".vtf")
+        .to_lowercase()
+}
+
+/// The family of every crosshair stem in `stems`, like `cpma` for `cpma_12` or `sniper` for
+/// `sniperbigOL`. A name without its `OL` suffix is cut at its first `_` or digit, then names
+/// that start with another, or share 6 or more leading characters with another, join the
+/// shortest such prefix.
+pub fn crosshair_families<'a>(stems: &[&'a str]) -> HashMap<&'a str, String> {
+    let bases = stems
+        .iter()
+        .map(|stem| {
+            let base = stem.strip_suffix("OL").unwrap_or(stem).to_lowercase();
+            let end = base
+                .find(|c: char| c == '_' || c.is_ascii_digit())
+                .unwrap_or(base.len());
+
+            base[..end].to_string()
+        })
+        .collect::<Vec<_>>();
+
+    let common_prefix = |a: &str, b: &str| {
+        let end = a
+            .char_indices()
+            .zip(b.chars())
+            .take_while(|((_, x), y)| x == y)
+            .last()
+            .map_or(0, |((i, c), _)| i + c.len_utf8());
+
+        a[..end].to_string()
+    };
+
+    stems
+        .iter()
+        .zip(&bases)
+        .map(|(stem, base)| {
+            let family = bases
+                .iter()
+                .filter(|other| *other != base && !other.is_empty())
+                .map(|other| common_prefix(base, other))
+                .filter(|prefix| {
+                    prefix.len() >= 6 || (!prefix.is_empty() && bases.iter().any(|b| b == prefix))
+                })
+                .min_by_key(String::len)
+                .unwrap_or_else(|| base.clone());
+
+            (*stem, family)
+        })
+        .collect()
+}
+
+/// A line of the crosshair list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CrosshairRow {
+    Family(String),
+    Crosshair(usize),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayMode {
+    #[default]
+    Crosshairs,
+    Explosions,
+}
+
+/// Everything the GUI shows, so widgets only render from it and never own any data.
+#[derive(Default)]
+pub struct Model {
+    weapons: Vec<Weapon>,
+    localization: Localization,
+    filter: WeaponFilter,
+    /// Indices of the weapons matching `filter`, in list order.
+    visible_weapons: Vec<usize>,
+    crosshairs: Vec<CrosshairItem>,
+    /// Thumbnail of every crosshair as a PNG, if it could be decoded.
+    crosshair_icons: Vec<Option<Vec<u8>>>,
+    crosshair_filter: CrosshairFilter,
+    catalogue: Catalogue,
+    crosshair_rows: Vec<CrosshairRow>,
+    /// Family of every crosshair by stem, worked out once all of them are loaded.
+    crosshair_families: HashMap<String, String>,
+    /// How many crosshairs each family has.
+    family_sizes: HashMap<String, usize>,
+    /// Materials of the crosshairs loaded so far.
+    crosshair_materials: HashSet<String>,
+    /// How many crosshairs are being loaded, once known.
+    crosshair_count: Option<usize>,
+    /// Weapons by the material of the crosshair they use.
+    crosshair_users: HashMap<String, Vec<usize>>,
+    current_weapon: Option<usize>,
+    selected_weapons: Vec<usize>,
+    selected_crosshair: Option<usize>,
+    display_mode: DisplayMode,
+}
+
+impl Model {
+    pub fn set_weapons(&mut self, weapons: Vec<Weapon>) {
+        self.weapons = weapons;
+        self.current_weapon = None;
+        self.selected_weapons.clear();
+        self.update_visible_weapons();
+        self.update_crosshair_users();
+    }
+
+    pub fn push_weapon(&mut self, weapon: Weapon) -> usize {
+        self.weapons.push(weapon);
+        self.update_visible_weapons();
+        self.update_crosshair_users();
+        self.weapons.len() - 1
+    }
+
+    /// Removes the weapon without associations named `key`, returning its index.
+    pub fn remove_unknown(&mut self, key: &str) -> Option<usize> {
+        let i = self
+            .weapons
+            .iter()
+            .position(|w| w.association.is_none() && w.file.name == key)?;
+
+        self.weapons.remove(i);
+        self.current_weapon = None;
+        self.selected_weapons.clear();
+        self.update_visible_weapons();
+        self.update_crosshair_users();
+
+        Some(i)
+    }
+
+    pub fn filter(&self) -> &WeaponFilter {
+        &self.filter
+    }
+
+    /// Narrows down the visible weapons, clearing the selection.
+    pub fn set_filter(&mut self, filter: WeaponFilter) {
+        self.filter = filter;
+        self.current_weapon = None;
+        self.selected_weapons.clear();
+        self.update_visible_weapons();
+    }
+
+    fn update_visible_weapons(&mut self) {
+        self.visible_weapons = self
+            .weapons
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| self.filter.matches(w))
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    fn update_crosshair_users(&mut self) {
+        self.crosshair_users.clear();
+
+        for (i, weapon) in self.weapons.iter().enumerate() {
+            self.crosshair_users
+                .entry(material_key(&weapon.file.crosshair))
+                .or_default()
+                .push(i);
+        }
+    }
+
+    pub fn visible_weapons(&self) -> &[usize] {
+        &self.visible_weapons
+    }
+
+    /// Row of weapon `i` in the list, if it's visible.
+    pub fn weapon_list_row(&self, i: usize) -> Option<usize> {
+        self.visible_weapons.iter().position(|v| *v == i)
+    }
+
+    /// Rereads the script of weapon `i` after it was written to.
+    pub fn reload_weapon(&mut self, i: usize) -> Result<()> {
+        let weapon = self
+            .weapons
+            .get_mut(i)
+            .ok_or_else(|| anyhow!("No weapon at index `{}`", i))?;
+
+        weapon.file = weapon.file.reload()?;
+        self.update_crosshair_users();
+
+        Ok(())
+    }
+
+    pub fn weapons(&self) -> &[Weapon] {
+        &self.weapons
+    }
+
+    pub fn weapon(&self, i: usize) -> Option<&Weapon> {
+        self.weapons.get(i)
+    }
+
+    pub fn localization(&self) -> &Localization {
+        &self.localization
+    }
+
+    pub fn set_localization(&mut self, localization: Localization) {
+        self.localization = localization;
+    }
+
+    pub fn select_weapons(&mut self, current: Option<usize>, selected: Vec<usize>) {
+        self.current_weapon = current.filter(|i| *i < self.weapons.len());
+        self.selected_weapons = selected
+            .into_iter()
+            .filter(|i| *i < self.weapons.len())
+            .collect();
+    }
+
+    pub fn current_weapon(&self) -> Option<(usize, &Weapon)> {
+        self.current_weapon.map(|i| (i, &self.weapons[i]))
+    }
+
+    pub fn selected_weapons(&self) -> Vec<usize> {
+        self.selected_weapons.clone()
+    }
+
+    /// The visible weapons of `class`.
+    pub fn all_class(&self, class: Option<Class>) -> Vec<usize> {
+        self.filter_weapons(|w| w.file.class == class)
+    }
+
+    /// The visible weapons of `slot`.
+    pub fn all_slot(&self, slot: Option<Slot>) -> Vec<usize> {
+        self.filter_weapons(|w| w.file.slot == slot)
+    }
+
+    /// Every visible weapon.
+    pub fn all_weapons(&self) -> Vec<usize> {
+        self.visible_weapons.clone()
+    }
+
+    /// The weapons of a group's `scripts`, listed or not.
+    pub fn group_weapons(&self, scripts: &[String]) -> Vec<usize> {
+        (0..self.weapons.len())
+            .filter(|i| scripts.contains(&self.weapons[*i].file.name))
+            .collect()
+    }
+
+    fn filter_weapons(&self, f: impl Fn(&Weapon) -> bool) -> Vec<usize> {
+        self.visible_weapons
+            .iter()
+            .copied()
+            .filter(|i| f(&self.weapons[*i]))
+            .collect()
+    }
+
+    /// Forgets every crosshair so they can be loaded again, keeping the filter and catalogue.
+    pub fn clear_crosshairs(&mut self) {
+        self.crosshairs.clear();
+        self.crosshair_icons.clear();
+        self.crosshair_materials.clear();
+        self.crosshair_families.clear();
+        self.family_sizes.clear();
+        self.crosshair_count = None;
+        self.selected_crosshair = None;
+        self.update_crosshair_rows();
+    }
+
+    /// Sets how many crosshairs are about to be pushed.
+    pub fn set_crosshair_count(&mut self, count: usize) {
+        self.crosshair_count = Some(count);
+    }
+
+    /// Whether every crosshair has been pushed, so missing ones can be told apart from ones still
+    /// loading.
+    pub fn crosshairs_loaded(&self) -> bool {
+        self.crosshair_count
+            .is_some_and(|count| self.crosshairs.len() >= count)
+    }
+
+    pub fn push_crosshair(&mut self, crosshair: CrosshairItem, icon: Option<Vec<u8>>) -> usize {
+        self.crosshair_materials
+            .insert(crosshair_material(&crosshair.name));
+        self.crosshairs.push(crosshair);
+        self.crosshair_icons.push(icon);
+
+        // Families compare every crosshair with every other, and rows sort every crosshair, so
+        // both wait for the last one.
+        if self.crosshair_count.is_none() || self.crosshairs_loaded() {
+            self.update_crosshair_families();
+            self.update_crosshair_rows();
+        }
+
+        self.crosshairs.len() - 1
+    }
+
+    fn update_crosshair_families(&mut self) {
+        let stems = self
+            .crosshairs
+            .iter()
+            .map(|c| crosshair_stem(&c.name))
+            .collect::<Vec<_>>();
+
+        let families = crosshair_families(&stems)
+            .into_iter()
+            .map(|(stem, family)| (stem.to_string(), family))
+            .collect::<HashMap<_, _>>();
+
+        let mut sizes = HashMap::new();
+        for family in families.values() {
+            *sizes.entry(family.clone()).or_insert(0) += 1;
+        }
+
+        self.crosshair_families = families;
+        self.family_sizes = sizes;
+    }
+
+    pub fn crosshair_icon(&self, i: usize) -> Option<&[u8]> {
+        self.crosshair_icons.get(i)?.as_deref()
+    }
+
+    pub fn crosshair_filter(&self) -> &CrosshairFilter {
+        &self.crosshair_filter
+    }
+
+    pub fn set_crosshair_filter(&mut self, filter: CrosshairFilter) {
+        self.crosshair_filter = filter;
+        self.update_crosshair_rows();
+    }
+
+    pub fn catalogue(&self) -> &Catalogue {
+        &self.catalogue
+    }
+
+    pub fn set_catalogue(&mut self, catalogue: Catalogue) {
+        self.catalogue = catalogue;
+        self.update_crosshair_rows();
+    }
+
+    pub fn crosshair_meta(&self, i: usize) -> Option<&CrosshairMeta> {
+        self.catalogue
+            .get(crosshair_stem(&self.crosshairs.get(i)?.name))
+    }
+
+    pub fn set_crosshair_meta(&mut self, i: usize, meta: CrosshairMeta) {
+        if let Some(crosshair) = self.crosshairs.get(i) {
+            self.catalogue.set(crosshair_stem(&crosshair.name), meta);
+            self.update_crosshair_rows();
+        }
+    }
+
+    fn update_crosshair_rows(&mut self) {
+        let mut visible = (0..self.crosshairs.len())
+            .filter(|i| {
+                self.crosshair_filter
+                    .matches(&self.crosshairs[*i].name, self.crosshair_meta(*i))
+            })
+            .collect::<Vec<_>>();
+
+        let stem = |i: usize| crosshair_stem(&self.crosshairs[i].name);
+        visible.sort_by(|a, b| natural_cmp(stem(*a), stem(*b)));
+
+        if !self.crosshair_filter.group {
+            self.crosshair_rows = visible.into_iter().map(CrosshairRow::Crosshair).collect();
+            return;
+        }
+
+        // Families come from every crosshair so searching doesn't split them up. Ones still
+        // loading have none yet.
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        let mut other = Vec::new();
+
+        for i in visible {
+            let family = self
+                .crosshair_families
+                .get(stem(i))
+                .filter(|f| self.family_sizes.get(*f).is_some_and(|&n| n >= 2));
+
+            match family {
+                None => other.push(i),
+                Some(family) => match groups.iter_mut().find(|(f, _)| f == family) {
+                    Some((_, members)) => members.push(i),
+                    None => groups.push((family.clone(), vec![i])),
+                },
+            }
+        }
+
+        groups.sort_by(|a, b| natural_cmp(&a.0, &b.0));
+
+        if !other.is_empty() {
+            groups.push(("other".to_string(), other));
+        }
+
+        self.crosshair_rows = groups
+            .into_iter()
+            .flat_map(|(family, members)| {
+                std::iter::once(CrosshairRow::Family(family))
+                    .chain(members.into_iter().map(CrosshairRow::Crosshair))
+            })
+            .collect();
+    }
+
+    pub fn crosshair_rows(&self) -> &[CrosshairRow] {
+        &self.crosshair_rows
+    }
+
+    /// Row of crosshair `i` in the list, if it's visible.
+    pub fn crosshair_list_row(&self, i: usize) -> Option<usize> {
+        self.crosshair_rows
+            .iter()
+            .position(|r| *r == CrosshairRow::Crosshair(i))
+    }
+
+    /// The `OL` outline version of crosshair `i`, or the plain version if `i` is the outline.
+    pub fn outline_variant(&self, i: usize) -> Option<usize> {
+        let stem = crosshair_stem(&self.crosshairs.get(i)?.name);
+
+        let variant = match stem.strip_suffix("OL") {
+            Some(plain) => plain.to_string(),
+            None => format!("{}OL", stem),
+        };
+
+        self.crosshairs
+            .iter()
+            .position(|c| crosshair_stem(&c.name) == variant)
+    }
+
+    /// The weapons using crosshair `i`.
+    pub fn crosshair_users(&self, i: usize) -> &[usize] {
+        self.crosshairs
+            .get(i)
+            .and_then(|c| self.crosshair_users.get(&crosshair_material(&c.name)))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether weapon `i` uses a crosshair that isn't in the crosshair folder. Stock `sprites`
+    /// textures come from the game's VPKs, so they never are.
+    pub fn missing_crosshair(&self, i: usize) -> bool {
+        let material = match self.weapons.get(i) {
+            Some(w) => material_key(&w.file.crosshair),
+            None => return false,
+        };
+
+        self.crosshairs_loaded()
+            && !material.starts_with("sprites/")
+            && !self.crosshair_materials.contains(&material)
+    }
+
+    pub fn crosshairs(&self) -> &[CrosshairItem] {
+        &self.crosshairs
+    }
+
+    pub fn select_crosshair(&mut self, i: Option<usize>) {
+        self.selected_crosshair = i.filter(|i| *i < self.crosshairs.len());
+    }
+
+    pub fn selected_crosshair(&self) -> Option<&CrosshairItem> {
+        self.selected_crosshair.map(|i| &self.crosshairs[i])
+    }
+
+    pub fn selected_crosshair_index(&self) -> Option<usize> {
+        self.selected_crosshair
+    }
+
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_mode = mode;
+    }
+
+    /// Browser text of weapon `i`, with weapons using explosions highlighted in explosion mode
+    /// and crosshairs that don't exist in red.
+    pub fn weapon_row(&self, i: usize) -> String {
+        let weapon = &self.weapons[i];
+
+        let highlight = if self.display_mode == DisplayMode::Explosions && weapon.uses_explosion() {
+            "@C4"
+        } else {
+            ""
+        };
+
+        let crosshair = if self.missing_crosshair(i) {
+            format!("@C1{} (missing)", weapon.crosshair_file_name())
+        } else {
+            weapon.crosshair_file_name()
+        };
+
+        format!(
+            "@f{0}{1}\t@f{0}{2}\t@f{3}",
+            highlight,
+            weapon.class_label(),
+            weapon.display(),
+            crosshair
+        )
+    }
+
+    /// Browser text of a line of the crosshair list, with favourites starred, and display names
+    /// and how many weapons use the crosshair after the file name.
+    pub fn crosshair_row(&self, row: &CrosshairRow) -> String {
+        let i = match row {
+            CrosshairRow::Family(family) => return format!("@b@.{}", family),
+            CrosshairRow::Crosshair(i) => *i,
+        };
+
+        let name = &self.crosshairs[i].name;
+
+        let users = match self.crosshair_users(i).len() {
+            0 => String::new(),
+            n => format!(" [{}]", n),
+        };
+
+        match self.crosshair_meta(i) {
+            Some(meta) => format!(
+                "@.{}{}{}{}",
+                if meta.favourite { "* " } else { "" },
+                name,
+                if meta.display.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", meta.display)
+                },
+                users
+            ),
+            None => format!("{}{}", name, users),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn model() -> Model {
+        let (weapons, errors) = load_weapons(
+            Path::new("resources/scripts"),
+            &Associations::builtin().unwrap(),
+        )
+        .unwrap();
+
+        assert!(errors.is_empty());
+
+        let mut model = Model::default();
+        model.set_weapons(weapons);
+
+        model
+    }
+
+    #[test]
+    fn weapon_names() {
+        let model = model();
+        let localization = Localization::parse(
+            "\"lang\" { \"Language\" \"German\" \"Tokens\" { \"TF_Weapon_SMG\" \"Maschinenpistole\" } }",
+        )
+        .unwrap();
+
+        let name = |script: &str| {
+            model
+                .weapons()
+                .iter()
+                .find(|w| w.file.name == script)
+                .unwrap()
+                .name(&localization)
+                .to_string()
+        };
+
+        assert_eq!(name("tf_weapon_smg"), "Maschinenpistole");
+        // Printnames that aren't tokens are names already.
+        assert_eq!(name("tf_weapon_passtime_gun"), "PASS Time");
+        // Tokens missing from the localisation fall back to `associations.json`.
+        assert_eq!(name("tf_weapon_scattergun"), "Scatterguns");
+    }
+
+    #[test]
+    fn weapon_row() {
+        let mut model = model();
+
+        let i = model
+            .weapons()
+            .iter()
+            .position(|w| w.file.name == "tf_weapon_rocketlauncher")
+            .unwrap();
+
+        assert_eq!(
+            model.weapon_row(i),
+            "@fSoldier\t@fRocket Launcher\t@fcrosshairs"
+        );
+
+        model.set_display_mode(DisplayMode::Explosions);
+
+        assert_eq!(
+            model.weapon_row(i),
+            "@f@C4Soldier\t@f@C4Rocket Launcher\t@fcrosshairs"
+        );
+    }
+
+    #[test]
+    fn filters() {
+        let model = model();
+
+        let medic = model.all_class(Some(Class::Medic));
+        assert!(!medic.is_empty());
+        assert!(medic
+            .iter()
+            .all(|i| model.weapon(*i).unwrap().class_label() == "Medic"));
+
+        let melee = model.all_slot(Some(Slot::Melee));
+        assert!(melee
+            .iter()
+            .any(|i| model.weapon(*i).unwrap().file.name == "tf_weapon_bat"));
+
+        assert_eq!(model.all_weapons().len(), model.weapons().len());
+    }
+
+    #[test]
+    fn weapon_filter() {
+        let mut model = model();
+
+        model.set_filter(WeaponFilter {
+            text: "force-a-nature".into(),
+            ..Default::default()
+        });
+
+        let visible = model.visible_weapons().to_vec();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(
+            model.weapon(visible[0]).unwrap().file.name,
+            "tf_weapon_scattergun"
+        );
+        assert_eq!(model.weapon_list_row(visible[0]), Some(0));
+        assert_eq!(model.all_weapons(), visible);
+
+        model.set_filter(WeaponFilter {
+            text: "SHOTGUN".into(),
+            class: Some(Class::Soldier),
+            slot: None,
+        });
+
+        assert!(!model.visible_weapons().is_empty());
+        assert!(model
+            .visible_weapons()
+            .iter()
+            .all(|i| model.weapon(*i).unwrap().file.class == Some(Class::Soldier)));
+        assert!(model.all_class(Some(Class::Pyro)).is_empty());
+
+        model.set_filter(WeaponFilter {
+            slot: Some(Slot::Melee),
+            ..Default::default()
+        });
+
+        assert_eq!(model.all_weapons(), model.all_slot(Some(Slot::Melee)));
+    }
+
+    fn crosshair(name: &str) -> CrosshairItem {
+        CrosshairItem {
+            name: name.into(),
+            path: name.into(),
+            size: (64, 64),
+        }
+    }
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["ql_10", "ql_2", "QL_1", "ql_1a", "bigcross"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+
+        assert_eq!(names, vec!["bigcross", "QL_1", "ql_1a", "ql_2", "ql_10"]);
+    }
+
+    #[test]
+    fn families() {
+        let stems = [
+            "cpma_1",
+            "cpma_12",
+            "sniper",
+            "sniperbigOL",
+            "snipercircle",
+            "xhairshadowdots",
+            "xhairshadowplus",
+            "crosshair5circleOL",
+            "Crossdot",
+            "dot",
+        ];
+        let families = crosshair_families(&stems);
+
+        assert_eq!(families["cpma_12"], "cpma");
+        assert_eq!(families["sniperbigOL"], "sniper");
+        assert_eq!(families["snipercircle"], "sniper");
+        assert_eq!(families["xhairshadowplus"], "xhairshadow");
+        assert_eq!(families["crosshair5circleOL"], "crosshair");
+        assert_eq!(families["Crossdot"], "crossdot");
+        assert_eq!(families["dot"], "dot");
+
+        let families = crosshair_families(&["crosséa", "crosséb"]);
+        assert_eq!(families["crosséa"], "crossé");
+    }
+
+    #[test]
+    fn crosshair_rows() {
+        let mut model = Model::default();
+
+        for name in ["ql_10.vtf", "dot.vtf", "ql_2.vtf", "ql_2OL.vtf"] {
+            model.push_crosshair(crosshair(name), None);
+        }
+
+        assert_eq!(
+            model.crosshair_rows(),
+            [1, 2, 3, 0].map(CrosshairRow::Crosshair)
+        );
+
+        model.set_crosshair_filter(CrosshairFilter {
+            text: "QL".into(),
+            group: true,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            model.crosshair_rows(),
+            &[
+                CrosshairRow::Family("ql".into()),
+                CrosshairRow::Crosshair(2),
+                CrosshairRow::Crosshair(3),
+                CrosshairRow::Crosshair(0),
+            ]
+        );
+        assert_eq!(model.crosshair_list_row(0), Some(3));
+        assert_eq!(model.crosshair_list_row(1), None);
+        assert_eq!(model.crosshair_row(&model.crosshair_rows()[0]), "@b@.ql");
+
+        model.set_crosshair_filter(CrosshairFilter {
+            group: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            model.crosshair_rows()[4..],
+            [
+                CrosshairRow::Family("other".into()),
+                CrosshairRow::Crosshair(1)
+            ]
+        );
+
+        // Families and rows wait until every crosshair is loaded.
+        model.clear_crosshairs();
+        model.set_crosshair_count(2);
+        model.push_crosshair(crosshair("ql_2.vtf"), None);
+        assert!(model.crosshair_rows().is_empty());
+
+        model.push_crosshair(crosshair("ql_10.vtf"), None);
+        assert_eq!(model.crosshair_rows()[0], CrosshairRow::Family("ql".into()));
+
+        for name in ["dot.vtf", "ql_2OL.vtf"] {
+            model.push_crosshair(crosshair(name), None);
+        }
+
+        assert_eq!(model.outline_variant(0), Some(3));
+        assert_eq!(model.outline_variant(3), Some(0));
+        assert_eq!(model.outline_variant(1), None);
+    }
+
+    #[test]
+    fn catalogue() {
+        let mut model = Model::default();
+
+        for name in ["ql_2.vtf", "dot.vtf", "cpma_1.vtf"] {
+            model.push_crosshair(crosshair(name), None);
+        }
+
+        model.set_catalogue(
+            Catalogue::parse(r#"{"ql_2": {"source": "Quake Live", "favourite": true}}"#).unwrap(),
+        );
+        model.set_crosshair_meta(
+            2,
+            CrosshairMeta {
+                display: "Classic".into(),
+                tags: vec!["Quake Live".into()],
+                ..Default::default()
+            },
+        );
+
+        model.set_crosshair_filter(CrosshairFilter {
+            label: Some("quake live".into()),
+            ..Default::default()
+        });
+        assert_eq!(model.crosshair_rows(), [2, 0].map(CrosshairRow::Crosshair));
+
+        model.set_crosshair_filter(CrosshairFilter {
+            text: "classic".into(),
+            ..Default::default()
+        });
+        assert_eq!(model.crosshair_rows(), [CrosshairRow::Crosshair(2)]);
+
+        model.set_crosshair_filter(CrosshairFilter {
+            favourites: true,
+            ..Default::default()
+        });
+        assert_eq!(model.crosshair_rows(), [CrosshairRow::Crosshair(0)]);
+
+        assert_eq!(
+            model.crosshair_row(&CrosshairRow::Crosshair(0)),
+            "@.* ql_2.vtf"
+        );
+        assert_eq!(
+            model.crosshair_row(&CrosshairRow::Crosshair(2)),
+            "@.cpma_1.vtf (Classic)"
+        );
+        assert_eq!(model.crosshair_row(&CrosshairRow::Crosshair(1)), "dot.vtf");
+    }
+
+    #[test]
+    fn crosshair_users() {
+        let (mut weapons, _) = load_weapons(
+            Path::new("resources/scripts"),
+            &Associations::builtin().unwrap(),
+        )
+        .unwrap();
+
+        weapons[0].file.crosshair = "vgui/replay/thumbnails/QL_2".into();
+        weapons[1].file.crosshair = "vgui\\replay\\thumbnails\\ql_2.vtf".into();
+        weapons[2].file.crosshair = "vgui/replay/thumbnails/deleted".into();
+
+        let mut model = Model::default();
+        model.set_weapons(weapons);
+        model.set_crosshair_count(2);
+
+        model.push_crosshair(crosshair("ql_2.vtf"), None);
+        assert!(!model.missing_crosshair(2));
+
+        model.push_crosshair(crosshair("dot.vtf"), None);
+
+        assert_eq!(model.crosshair_users(0), [0, 1]);
+        assert!(model.crosshair_users(1).is_empty());
+        assert_eq!(
+            model.crosshair_row(&CrosshairRow::Crosshair(0)),
+            "ql_2.vtf [2]"
+        );
+
+        assert!(!model.missing_crosshair(0));
+        assert!(model.missing_crosshair(2));
+        assert!(!model.missing_crosshair(3));
+        assert!(model.weapon_row(2).ends_with("@f@C1deleted (missing)"));
+
+        model.clear_crosshairs();
+        assert!(model.crosshair_rows().is_empty());
+        assert!(!model.missing_crosshair(2));
+    }
+
+    #[test]
+    fn selection() {
+        let mut model = model();
+
+        model.select_weapons(Some(2), vec![1, 2, 1000]);
+
+        assert_eq!(model.current_weapon().unwrap().0, 2);
+        assert_eq!(model.selected_weapons(), vec![1, 2]);
+
+        model.select_crosshair(Some(0));
+        assert!(model.selected_crosshair().is_none());
+    }
+}
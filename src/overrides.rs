@@ -0,0 +1,437 @@
+use crate::texture::{u16_at, u32_at};
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+const VPK_SIGNATURE: u32 = 0x55aa1234;
+
+/// A weapon script that other mods in the custom folder also have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Override {
+    pub script: String,
+    /// Folders and VPKs with the script, in load order, so the first one wins.
+    pub mods: Vec<PathBuf>,
+}
+
+impl Override {
+    pub fn winner(&self) -> &Path {
+        &self.mods[0]
+    }
+
+    pub fn is_vpk(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("vpk"))
+    }
+
+    /// The winning copy of the script, when it's in a folder that can be edited.
+    pub fn winning_script(&self) -> Option<PathBuf> {
+        let winner = self.winner();
+
+        if Self::is_vpk(winner) {
+            None
+        } else {
+            Some(winner.join("scripts").join(&self.script))
+        }
+    }
+}
+
+/// Whether `name` is a VPK the game mounts, rather than one of the numbered archives of a
+/// `_dir.vpk`.
+fn is_mounted_vpk(name: &str) -> bool {
+    let Some(stem) = name.strip_suffix(".vpk") else {
+        return false;
+    };
+
+    match stem.rsplit_once('_') {
+        Some((_, n)) => n.len() != 3 || !n.bytes().all(|b| b.is_ascii_digit()),
+        None => true,
+    }
+}
+
+fn read_string(tree: &[u8], offset: &mut usize) -> Result<String> {
+    let rest = tree.get(*offset..).context("Unexpected end of VPK tree")?;
+    let len = rest
+        .iter()
+        .position(|&b| b == 0)
+        .context("Unterminated string in VPK tree")?;
+
+    let s = String::from_utf8_lossy(&rest[..len]).into_owned();
+    *offset += len + 1;
+
+    Ok(s)
+}
+
+/// Paths of the files in a VPK, read from its directory tree.
+pub fn vpk_files(path: &Path) -> Result<Vec<String>> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let mut header = [0; 12];
+    file.read_exact(&mut header)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    if u32_at(&header, 0)? != VPK_SIGNATURE {
+        bail!("{} is not a VPK", path.display());
+    }
+
+    let version = u32_at(&header, 4)?;
+    let tree_size = u32_at(&header, 8)? as usize;
+
+    // Version 2 has 16 more bytes of header, after which the tree starts just the same.
+    let header_len = match version {
+        1 => 12,
+        2 => {
+            file.read_exact(&mut [0; 16])
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            28
+        }
+        _ => bail!("Unsupported VPK version {} in {}", version, path.display()),
+    };
+
+    // Checked before allocating, as the size comes straight from the file.
+    let len = file
+        .metadata()
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .len();
+
+    if tree_size as u64 > len.saturating_sub(header_len) {
+        bail!(
+            "{} has a {} byte tree but is only {} bytes long",
+            path.display(),
+            tree_size,
+            len
+        );
+    }
+
+    let mut tree = vec![0; tree_size];
+    file.read_exact(&mut tree)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut files = Vec::new();
+    let mut offset = 0;
+
+    // Extensions, each holding folders, each holding file names, all ended by an empty string.
+    loop {
+        let extension = read_string(&tree, &mut offset)?;
+        if extension.is_empty() {
+            break;
+        }
+
+        loop {
+            let dir = read_string(&tree, &mut offset)?;
+            if dir.is_empty() {
+                break;
+            }
+
+            loop {
+                let name = read_string(&tree, &mut offset)?;
+                if name.is_empty() {
+                    break;
+                }
+
+                let preload = u16_at(&tree, offset + 4)? as usize;
+                offset += 18 + preload;
+
+                files.push(match dir.as_str() {
+                    " " => format!("{}.{}", name, extension),
+                    _ => format!("{}/{}.{}", dir, name, extension),
+                });
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// The weapon scripts a folder or VPK in the custom folder has, lowercased.
+fn mod_scripts(path: &Path) -> Result<Vec<String>> {
+    if Override::is_vpk(path) {
+        return Ok(vpk_files(path)?
+            .into_iter()
+            .filter_map(|f| {
+                f.to_ascii_lowercase()
+                    .strip_prefix("scripts/")
+                    .map(str::to_string)
+            })
+            .collect());
+    }
+
+    let dir = path.join("scripts");
+
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    Ok(dir
+        .read_dir()
+        .with_context(|| format!("Failed to read folder `{}`", dir.display()))?
+        .filter_map(|f| f.ok())
+        .map(|f| f.file_name().to_string_lossy().to_ascii_lowercase())
+        .collect())
+}
+
+/// Finds the weapon scripts of `ours` that other folders and VPKs next to it also have, the
+/// custom folder being mounted in alphabetical order. Returns them along with the mods that
+/// couldn't be read.
+pub fn scan(ours: &Path) -> Result<(Vec<Override>, Vec<String>)> {
+    let custom_dir = ours.parent().context("The binary has no parent folder")?;
+    let scripts_dir = ours.join("scripts");
+
+    let mut mods = custom_dir
+        .read_dir()
+        .with_context(|| format!("Failed to read folder `{}`", custom_dir.display()))?
+        .filter_map(|f| f.ok().map(|f| f.path()))
+        .filter(|p| {
+            let name = p
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_ascii_lowercase();
+            p.is_dir() || is_mounted_vpk(&name)
+        })
+        .collect::<Vec<_>>();
+    mods.sort_by_key(|p| {
+        p.file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_ascii_lowercase()
+    });
+
+    let mut errors = Vec::new();
+    let mut scripts = Vec::new();
+
+    for path in mods {
+        if path == ours {
+            scripts.push((path, Vec::new()));
+            continue;
+        }
+
+        match mod_scripts(&path) {
+            Ok(s) => scripts.push((path, s)),
+            Err(e) => errors.push(format!("Skipping {}; {:#}", path.display(), e)),
+        }
+    }
+
+    let mut names = scripts_dir
+        .read_dir()
+        .with_context(|| format!("Failed to read folder `{}`", scripts_dir.display()))?
+        .filter_map(|f| f.ok())
+        .map(|f| f.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".txt"))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    let overrides = names
+        .into_iter()
+        .filter_map(|script| {
+            let key = script.to_ascii_lowercase();
+
+            let mods = scripts
+                .iter()
+                .filter(|(path, s)| path == ours || s.contains(&key))
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>();
+
+            (mods.len() > 1).then_some(Override { script, mods })
+        })
+        .collect();
+
+    Ok((overrides, errors))
+}
+
+/// Copies the crosshair and explosion values of our copy of the script into the winning one.
+/// Returns the fields the winning copy doesn't have.
+pub fn merge(ours: &Path, o: &Override) -> Result<Vec<String>> {
+    let theirs = match o.winning_script() {
+        Some(t) => t,
+        None => bail!(
+            "Can't merge {} into {}, a VPK",
+            o.script,
+            o.winner().display()
+        ),
+    };
+
+    let path = ours.join("scripts").join(&o.script);
+    let s =
+        fs::read_to_string(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    // The other mod may use a different case for the file name.
+    let theirs = theirs
+        .parent()
+        .unwrap()
+        .read_dir()
+        .with_context(|| format!("Failed to read folder `{}`", o.winner().display()))?
+        .filter_map(|f| f.ok().map(|f| f.path()))
+        .find(|p| p.file_name().unwrap().eq_ignore_ascii_case(&o.script))
+        .unwrap_or(theirs);
+
+    let their_s = fs::read_to_string(&theirs)
+        .with_context(|| format!("Failed to open {}", theirs.display()))?;
+
+    let (merged, missing) = crate::rebase::merge(&s, &their_s);
+
+    fs::write(&theirs, merged).with_context(|| format!("Failed to write {}", theirs.display()))?;
+
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A single-file version 1 VPK holding `files`, with no data.
+    fn vpk(files: &[(&str, &str, &str)]) -> Vec<u8> {
+        let mut tree = Vec::new();
+
+        for (extension, dir, name) in files {
+            for s in [extension, dir, name] {
+                tree.extend_from_slice(s.as_bytes());
+                tree.push(0);
+            }
+
+            // CRC, preload size, archive index, offset, length and terminator.
+            tree.extend_from_slice(&[0; 4]);
+            tree.extend_from_slice(&0u16.to_le_bytes());
+            tree.extend_from_slice(&0x7fffu16.to_le_bytes());
+            tree.extend_from_slice(&[0; 8]);
+            tree.extend_from_slice(&0xffffu16.to_le_bytes());
+
+            tree.extend_from_slice(&[0, 0]);
+        }
+        tree.push(0);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&VPK_SIGNATURE.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&(tree.len() as u32).to_le_bytes());
+        data.extend_from_slice(&tree);
+
+        data
+    }
+
+    #[test]
+    fn vpk_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("hud.vpk");
+
+        fs::write(
+            &path,
+            vpk(&[
+                ("txt", "scripts", "tf_weapon_bat"),
+                ("res", "resource/ui", "hudplayerhealth"),
+                ("txt", " ", "readme"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            super::vpk_files(&path).unwrap(),
+            [
+                "scripts/tf_weapon_bat.txt",
+                "resource/ui/hudplayerhealth.res",
+                "readme.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_vpk() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("hud.vpk");
+
+        // Preload data running past the end of the tree.
+        let mut data = vpk(&[("txt", "scripts", "tf_weapon_bat")]);
+        let preload = 12 + "txt\0scripts\0tf_weapon_bat\0".len() + 4;
+        data[preload..preload + 2].copy_from_slice(&1000u16.to_le_bytes());
+        fs::write(&path, &data).unwrap();
+
+        assert!(super::vpk_files(&path).is_err());
+
+        // A tree cut off in the middle of a name.
+        let mut data = vpk(&[("txt", "scripts", "tf_weapon_bat")]);
+        data.truncate(12 + 10);
+        data[8..12].copy_from_slice(&10u32.to_le_bytes());
+        fs::write(&path, &data).unwrap();
+
+        assert!(super::vpk_files(&path).is_err());
+
+        // A tree size far past the end of the file.
+        let mut data = vpk(&[("txt", "scripts", "tf_weapon_bat")]);
+        data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &data).unwrap();
+
+        let error = super::vpk_files(&path).unwrap_err().to_string();
+        assert!(error.contains("byte tree"), "{}", error);
+    }
+
+    #[test]
+    fn mounted_vpks() {
+        assert!(is_mounted_vpk("hud.vpk"));
+        assert!(is_mounted_vpk("hud_dir.vpk"));
+        assert!(is_mounted_vpk("my_hud_v2.vpk"));
+        assert!(!is_mounted_vpk("hud_000.vpk"));
+        assert!(!is_mounted_vpk("hud"));
+    }
+
+    #[test]
+    fn scan() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let custom = temp_dir.path();
+
+        for dir in [
+            "crosshairs/scripts",
+            "a_hud/scripts",
+            "z_mod/scripts",
+            "empty",
+        ] {
+            fs::create_dir_all(custom.join(dir)).unwrap();
+        }
+
+        for script in [
+            "tf_weapon_bat.txt",
+            "tf_weapon_smg.txt",
+            "tf_weapon_rpg.txt",
+        ] {
+            fs::write(custom.join("crosshairs/scripts").join(script), "").unwrap();
+        }
+
+        fs::write(custom.join("a_hud/scripts/TF_Weapon_Bat.txt"), "").unwrap();
+        fs::write(custom.join("z_mod/scripts/tf_weapon_smg.txt"), "").unwrap();
+        fs::write(
+            custom.join("b_sounds.vpk"),
+            vpk(&[("txt", "scripts", "tf_weapon_smg")]),
+        )
+        .unwrap();
+        fs::write(custom.join("broken.vpk"), "not a vpk").unwrap();
+
+        let ours = custom.join("crosshairs");
+        let (overrides, errors) = super::scan(&ours).unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            overrides,
+            [
+                Override {
+                    script: "tf_weapon_bat.txt".into(),
+                    mods: vec![custom.join("a_hud"), ours.clone()],
+                },
+                Override {
+                    script: "tf_weapon_smg.txt".into(),
+                    mods: vec![
+                        custom.join("b_sounds.vpk"),
+                        ours.clone(),
+                        custom.join("z_mod")
+                    ],
+                },
+            ]
+        );
+        assert_eq!(
+            overrides[0].winning_script(),
+            Some(custom.join("a_hud/scripts/tf_weapon_bat.txt"))
+        );
+        assert_eq!(overrides[1].winning_script(), None);
+    }
+}
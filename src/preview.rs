@@ -0,0 +1,136 @@
+use image::{Rgba, RgbaImage};
+
+/// Size of a checkerboard square, in preview pixels.
+const CHECKER_SIZE: u32 = 8;
+
+#[derive(Clone, Default)]
+pub enum Background {
+    #[default]
+    Checkerboard,
+    Black,
+    White,
+    SkyBlue,
+    /// A screenshot, the crosshair being drawn over its centre like in game.
+    Screenshot(RgbaImage),
+}
+
+impl Background {
+    /// Names of the backgrounds that don't need a screenshot, in the order of `from_index`.
+    pub const NAMES: [&'static str; 4] = ["Checkerboard", "Black", "White", "Sky blue"];
+
+    pub fn from_index(i: usize) -> Self {
+        match i {
+            1 => Self::Black,
+            2 => Self::White,
+            3 => Self::SkyBlue,
+            _ => Self::Checkerboard,
+        }
+    }
+
+    pub(crate) fn pixel(&self, x: u32, y: u32, width: u32, height: u32) -> [u8; 3] {
+        match self {
+            Self::Checkerboard => {
+                if (x / CHECKER_SIZE + y / CHECKER_SIZE) & 1 == 0 {
+                    [204, 204, 204]
+                } else {
+                    [255, 255, 255]
+                }
+            }
+            Self::Black => [0, 0, 0],
+            Self::White => [255, 255, 255],
+            Self::SkyBlue => [135, 206, 235],
+            Self::Screenshot(screenshot) => {
+                // Centre the area on the screenshot, wrapping around if it's smaller.
+                let sx = (x as i64 + (screenshot.width() as i64 - width as i64) / 2)
+                    .rem_euclid(screenshot.width().max(1) as i64);
+                let sy = (y as i64 + (screenshot.height() as i64 - height as i64) / 2)
+                    .rem_euclid(screenshot.height().max(1) as i64);
+
+                let p = screenshot.get_pixel(sx as u32, sy as u32).0;
+                [p[0], p[1], p[2]]
+            }
+        }
+    }
+}
+
+/// Scales `image` up `zoom` times without smoothing, so single pixels stay visible.
+pub fn scale_nearest(image: &RgbaImage, zoom: u32) -> RgbaImage {
+    let zoom = zoom.max(1);
+
+    RgbaImage::from_fn(image.width() * zoom, image.height() * zoom, |x, y| {
+        *image.get_pixel(x / zoom, y / zoom)
+    })
+}
+
+/// The alpha channel of `image` as an opaque grayscale image.
+pub fn alpha_only(image: &RgbaImage) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let a = image.get_pixel(x, y).0[3];
+        Rgba([a, a, a, 255])
+    })
+}
+
+/// Blends `src` over an opaque `dst`.
+pub fn blend(dst: [u8; 3], src: Rgba<u8>) -> Rgba<u8> {
+    let [r, g, b, a] = src.0;
+    let mix = |d: u8, s: u8| ((s as u32 * a as u32 + d as u32 * (255 - a as u32)) / 255) as u8;
+
+    Rgba([mix(dst[0], r), mix(dst[1], g), mix(dst[2], b), 255])
+}
+
+/// `crosshair` scaled up `zoom` times and drawn over `background`, or its alpha channel alone.
+pub fn render(crosshair: &RgbaImage, zoom: u32, background: &Background, alpha: bool) -> RgbaImage {
+    let scaled = scale_nearest(crosshair, zoom);
+
+    if alpha {
+        return alpha_only(&scaled);
+    }
+
+    let (width, height) = scaled.dimensions();
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        blend(
+            background.pixel(x, y, width, height),
+            *scaled.get_pixel(x, y),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let mut crosshair = RgbaImage::new(2, 2);
+        crosshair.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        crosshair.put_pixel(1, 0, Rgba([255, 255, 255, 128]));
+
+        let image = super::render(&crosshair, 4, &Background::Black, false);
+
+        assert_eq!(image.dimensions(), (8, 8));
+        assert_eq!(image.get_pixel(3, 3).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(4, 0).0, [128, 128, 128, 255]);
+        assert_eq!(image.get_pixel(7, 7).0, [0, 0, 0, 255]);
+
+        let alpha = super::render(&crosshair, 1, &Background::White, true);
+
+        assert_eq!(alpha.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(alpha.get_pixel(1, 0).0, [128, 128, 128, 255]);
+        assert_eq!(alpha.get_pixel(1, 1).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn screenshot() {
+        let screenshot = RgbaImage::from_fn(6, 6, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let image = super::render(
+            &RgbaImage::new(2, 2),
+            1,
+            &Background::Screenshot(screenshot),
+            false,
+        );
+
+        assert_eq!(image.get_pixel(0, 0).0, [2, 2, 0, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [3, 3, 0, 255]);
+    }
+}
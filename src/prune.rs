@@ -0,0 +1,385 @@
+use crate::manifest::{remove_empty_parents, Manifest};
+use crate::model::{material_key, Weapon};
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+/// Where crosshairs are kept in a custom folder.
+const CROSSHAIR_DIR: &str = "materials/vgui/replay/thumbnails";
+/// Where pruned files are moved to, out of the way of the game, which only reads known folders.
+pub const PRUNED_DIR: &str = "pruned";
+const MANIFEST: &str = "manifest.json";
+
+/// Crosshair files of a custom folder split by whether a weapon script uses them, relative to
+/// the custom folder.
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub keep: Vec<PathBuf>,
+    pub prune: Vec<PathBuf>,
+}
+
+fn is_vmt(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("vmt"))
+}
+
+/// The material a VMT draws, from its `$basetexture`.
+fn base_texture(vmt: &str) -> Option<String> {
+    vmt.lines().find_map(|line| {
+        let line = line.trim().trim_start_matches('"');
+
+        if !line.to_ascii_lowercase().starts_with("$basetexture") {
+            return None;
+        }
+
+        let value = line["$basetexture".len()..]
+            .trim_start_matches('"')
+            .trim()
+            .trim_matches('"');
+
+        Some(material_key(value))
+    })
+}
+
+/// Plans pruning around the crosshairs of `weapons`, `errors` being those of the weapon scripts
+/// that failed to load. Refuses while there are any, as the crosshairs they use look unused.
+pub fn plan_weapons(root: &Path, weapons: &[Weapon], errors: &[String]) -> Result<Plan> {
+    if !errors.is_empty() {
+        bail!(
+            "Not pruning while weapon scripts fail to parse:\n{}",
+            errors.join("\n")
+        );
+    }
+
+    plan(root, weapons.iter().map(|w| w.file.crosshair.as_str()))
+}
+
+/// Splits the crosshairs in `root` into ones `materials` use and ones nothing does. Used VMTs
+/// keep the texture they draw, so variants made to reuse another crosshair's texture keep
+/// working.
+pub fn plan<'a>(root: &Path, materials: impl IntoIterator<Item = &'a str>) -> Result<Plan> {
+    let dir = root.join(CROSSHAIR_DIR);
+
+    let mut files = dir
+        .read_dir()
+        .with_context(|| format!("Failed to read folder `{}`", dir.display()))?
+        .filter_map(|f| f.ok().map(|f| f.path()))
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("vtf") || e.eq_ignore_ascii_case("vmt"))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let material = |path: &Path| {
+        let stem = path.file_stem().unwrap().to_string_lossy();
+        material_key(&format!("vgui/replay/thumbnails/{}", stem))
+    };
+
+    let materials = materials
+        .into_iter()
+        .map(material_key)
+        .collect::<HashSet<_>>();
+    let mut textures = materials.clone();
+
+    for vmt in files.iter().filter(|p| is_vmt(p)) {
+        if !materials.contains(&material(vmt)) {
+            continue;
+        }
+
+        let s =
+            fs::read_to_string(vmt).with_context(|| format!("Failed to open {}", vmt.display()))?;
+
+        if let Some(texture) = base_texture(&s) {
+            textures.insert(texture);
+        }
+    }
+
+    let mut plan = Plan::default();
+
+    for path in files {
+        let used = if is_vmt(&path) { &materials } else { &textures };
+
+        let relative = path.strip_prefix(root).unwrap().to_path_buf();
+
+        if used.contains(&material(&path)) {
+            plan.keep.push(relative);
+        } else {
+            plan.prune.push(relative);
+        }
+    }
+
+    Ok(plan)
+}
+
+fn manifest_path(root: &Path) -> PathBuf {
+    root.join(PRUNED_DIR).join(MANIFEST)
+}
+
+fn read_manifest(root: &Path) -> Result<Vec<PathBuf>> {
+    let path = manifest_path(root);
+
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let s = fs::read_to_string(&path).with_context(|| format!("Failed to open {}", MANIFEST))?;
+    let json = json::parse(&s).with_context(|| format!("Failed to parse {}", MANIFEST))?;
+
+    if !json["files"].is_array() {
+        bail!("Failed to parse {}; missing or invalid `files`", MANIFEST);
+    }
+
+    Ok(json["files"]
+        .members()
+        .filter_map(|f| f.as_str())
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn write_manifest(root: &Path, files: &[PathBuf]) -> Result<()> {
+    let path = manifest_path(root);
+
+    if files.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+
+        return Ok(());
+    }
+
+    let mut json = json::JsonValue::new_object();
+    json["files"] = files
+        .iter()
+        .map(|f| f.to_string_lossy().replace('\\', "/"))
+        .collect::<Vec<_>>()
+        .into();
+
+    fs::write(&path, json::stringify_pretty(json, 4))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        bail!("`{}` already exists", to.display());
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create folder `{}`", parent.display()))?;
+    }
+
+    fs::rename(from, to).with_context(|| format!("Failed to move {}", from.display()))
+}
+
+/// Moves the files `plan` prunes into the `pruned` folder of `root`, adding them to its
+/// manifest. Returns the files that couldn't be moved.
+pub fn prune(root: &Path, plan: &Plan) -> Result<Vec<String>> {
+    let mut manifest = read_manifest(root)?;
+    let mut installed = Manifest::load(root)?;
+    let mut errors = Vec::new();
+
+    for file in &plan.prune {
+        let (from, to) = (root.join(file), root.join(PRUNED_DIR).join(file));
+
+        match move_file(&from, &to) {
+            Ok(()) => {
+                manifest.push(file.clone());
+                installed.rename(root, &from, &to);
+            }
+            Err(e) => errors.push(format!("Skipping {}; {:#}", file.display(), e)),
+        }
+    }
+
+    write_manifest(root, &manifest)?;
+    record_manifest(root, installed)?;
+
+    Ok(errors)
+}
+
+/// Moves every file in the manifest of `root` back where it was pruned from, returning the
+/// files restored and the ones that couldn't be, which stay in the manifest.
+pub fn restore(root: &Path) -> Result<(Vec<PathBuf>, Vec<String>)> {
+    let manifest = read_manifest(root)?;
+
+    if manifest.is_empty() {
+        bail!(
+            "Nothing to restore in `{}`",
+            root.join(PRUNED_DIR).display()
+        );
+    }
+
+    let mut installed = Manifest::load(root)?;
+    let mut restored = Vec::new();
+    let mut remaining = Vec::new();
+    let mut errors = Vec::new();
+
+    for file in manifest {
+        let (from, to) = (root.join(PRUNED_DIR).join(&file), root.join(&file));
+
+        match move_file(&from, &to) {
+            Ok(()) => {
+                installed.rename(root, &from, &to);
+                remove_empty_parents(root, &from);
+                restored.push(file);
+            }
+            Err(e) => {
+                errors.push(format!("Skipping {}; {:#}", file.display(), e));
+                remaining.push(file);
+            }
+        }
+    }
+
+    write_manifest(root, &remaining)?;
+    record_manifest(root, installed)?;
+
+    // The `pruned` folder goes once its manifest does.
+    remove_empty_parents(root, &manifest_path(root));
+
+    Ok((restored, errors))
+}
+
+/// Keeps the install manifest following the moved files and the prune manifest.
+fn record_manifest(root: &Path, mut installed: Manifest) -> Result<()> {
+    let path = manifest_path(root);
+
+    if path.exists() {
+        installed.record(root, &path, true)?;
+    } else {
+        installed.remove(root, &path);
+    }
+
+    installed.save(root)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn custom_folder() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join(CROSSHAIR_DIR);
+        fs::create_dir_all(&dir).unwrap();
+
+        for name in ["dot", "circle", "ql_2", "ql_2_red"] {
+            fs::write(dir.join(name).with_extension("vtf"), name).unwrap();
+            fs::write(
+                dir.join(name).with_extension("vmt"),
+                format!(
+                    "\"UnlitGeneric\"\n{{\n\t\"$basetexture\" \"vgui\\replay\\thumbnails\\{}\"\n}}",
+                    name
+                ),
+            )
+            .unwrap();
+        }
+
+        // A variant reusing another crosshair's texture.
+        fs::write(
+            dir.join("dot_big.vmt"),
+            "UnlitGeneric\n{\n\t$baseTexture vgui/replay/thumbnails/ql_2_red\n}",
+        )
+        .unwrap();
+        fs::write(dir.join("crosshairs.json"), "{}").unwrap();
+
+        temp_dir
+    }
+
+    #[test]
+    fn plan() {
+        let root = custom_folder();
+
+        let plan = super::plan(
+            root.path(),
+            [
+                "vgui/replay/thumbnails/DOT",
+                "vgui\\replay\\thumbnails\\dot_big",
+                "sprites/crosshairs",
+            ],
+        )
+        .unwrap();
+
+        let names = |files: &[PathBuf]| {
+            files
+                .iter()
+                .map(|f| f.file_name().unwrap().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(&plan.keep),
+            ["dot.vmt", "dot.vtf", "dot_big.vmt", "ql_2_red.vtf"]
+        );
+        assert_eq!(
+            names(&plan.prune),
+            [
+                "circle.vmt",
+                "circle.vtf",
+                "ql_2.vmt",
+                "ql_2.vtf",
+                "ql_2_red.vmt"
+            ]
+        );
+    }
+
+    #[test]
+    fn plan_weapons() {
+        let root = custom_folder();
+
+        // With no weapons, every crosshair is unused.
+        let plan = super::plan_weapons(root.path(), &[], &[]).unwrap();
+        assert!(plan.keep.is_empty());
+        assert_eq!(plan.prune.len(), 9);
+
+        let e = super::plan_weapons(root.path(), &[], &["Skipping tf_weapon_bat; oops".into()])
+            .unwrap_err();
+        assert!(e.to_string().ends_with("Skipping tf_weapon_bat; oops"));
+    }
+
+    #[test]
+    fn prune_and_restore() {
+        let root = custom_folder();
+        let dir = root.path().join(CROSSHAIR_DIR);
+
+        Manifest::scan(root.path(), "dist")
+            .unwrap()
+            .save(root.path())
+            .unwrap();
+
+        let plan = super::plan(root.path(), ["vgui/replay/thumbnails/dot"]).unwrap();
+        assert!(super::prune(root.path(), &plan).unwrap().is_empty());
+
+        assert!(dir.join("dot.vtf").exists());
+        assert!(!dir.join("circle.vtf").exists());
+        assert!(root
+            .path()
+            .join(PRUNED_DIR)
+            .join(CROSSHAIR_DIR)
+            .join("circle.vtf")
+            .exists());
+        assert_eq!(read_manifest(root.path()).unwrap().len(), plan.prune.len());
+
+        // The install manifest follows the moved files.
+        let installed = Manifest::load(root.path()).unwrap();
+        assert!(installed.changes(root.path()).is_empty());
+        assert!(installed
+            .get(root.path(), &manifest_path(root.path()))
+            .is_some_and(|e| e.created));
+
+        // A file that came back some other way stays pruned.
+        fs::write(dir.join("circle.vtf"), "new").unwrap();
+
+        let (restored, errors) = restore(root.path()).unwrap();
+
+        assert_eq!(restored.len(), plan.prune.len() - 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(fs::read_to_string(dir.join("ql_2.vtf")).unwrap(), "ql_2");
+        assert_eq!(
+            read_manifest(root.path()).unwrap(),
+            [Path::new(CROSSHAIR_DIR).join("circle.vtf")]
+        );
+    }
+}
@@ -0,0 +1,443 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+/// Values the tool edits in the `crosshair` block of a weapon script.
+const CROSSHAIR_FIELDS: [&str; 5] = ["file", "x", "y", "width", "height"];
+/// Values the tool edits anywhere in a weapon script.
+const EXPLOSION_FIELDS: [&str; 3] = [
+    "ExplosionEffect",
+    "ExplosionPlayerEffect",
+    "ExplosionWaterEffect",
+];
+
+/// What happened to a weapon script when rebasing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rebased {
+    /// No edits to keep, so the new stock script as is.
+    Stock,
+    /// These fields were edited and are applied to the new stock script.
+    Edited(Vec<String>),
+    /// These fields were edited and are applied, but the stock script changed what they touch,
+    /// so the result needs checking.
+    Conflict(Vec<String>, String),
+    /// Only in the new stock scripts, so copied over.
+    Added,
+    /// Not in the new stock scripts, so left alone.
+    Removed,
+}
+
+/// A field the tool edits, the line it's on and its value.
+struct Field {
+    name: String,
+    line: usize,
+    value: String,
+}
+
+/// Where the token starting at `start` of `line` ends, and its text without quotes. Quoted
+/// tokens may have spaces.
+fn token(line: &str, start: usize) -> (usize, &str) {
+    let rest = &line[start..];
+
+    match rest.strip_prefix('"') {
+        Some(quoted) => match quoted.find('"') {
+            Some(end) => (start + end + 2, &quoted[..end]),
+            None => (line.len(), quoted),
+        },
+        None => {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (start + end, &rest[..end])
+        }
+    }
+}
+
+/// Bytes of the value token of a `"key" "value"` line, quotes and all, and the value.
+fn value_token(line: &str) -> Option<(Range<usize>, &str)> {
+    let key_start = line.len() - line.trim_start().len();
+    let (key_end, _) = token(line, key_start);
+
+    let start = key_end + (line[key_end..].len() - line[key_end..].trim_start().len());
+
+    if start == line.len() || line[start..].starts_with("//") {
+        return None;
+    }
+
+    let (end, value) = token(line, start);
+
+    Some((start..end, value))
+}
+
+fn value(line: &str) -> Option<String> {
+    value_token(line).map(|(_, v)| v.to_string())
+}
+
+fn is_key(line: &str, key: &str) -> bool {
+    line.strip_prefix('"')
+        .and_then(|l| l.strip_prefix(key))
+        .is_some_and(|l| l.starts_with('"'))
+}
+
+/// The fields the tool edits in `script`, with crosshair fields prefixed by `crosshair.`, along
+/// with the lines of the `crosshair` block.
+fn fields(script: &str) -> (Vec<Field>, Vec<String>) {
+    let mut fields = Vec::new();
+    let mut block = Vec::new();
+    let mut in_crosshair = false;
+
+    for (i, line) in script.lines().map(str::trim).enumerate() {
+        if in_crosshair {
+            if line.contains('}') {
+                in_crosshair = false;
+                continue;
+            }
+
+            block.push(line.split_whitespace().collect::<Vec<_>>().join(" "));
+
+            if let Some(name) = CROSSHAIR_FIELDS.iter().find(|f| is_key(line, f)) {
+                fields.push(Field {
+                    name: format!("crosshair.{}", name),
+                    line: i,
+                    value: value(line).unwrap_or_default(),
+                });
+            }
+        } else if is_key(line, "crosshair") {
+            in_crosshair = true;
+        } else if let Some(name) = EXPLOSION_FIELDS.iter().find(|f| is_key(line, f)) {
+            fields.push(Field {
+                name: name.to_string(),
+                line: i,
+                value: value(line).unwrap_or_default(),
+            });
+        }
+    }
+
+    // The opening brace of the block.
+    block.retain(|l| l != "{");
+
+    (fields, block)
+}
+
+fn get<'a>(fields: &'a [Field], name: &str) -> Option<&'a Field> {
+    fields.iter().find(|f| f.name == name)
+}
+
+/// Sets the `values` of fields in `script`, keeping its formatting. Returns the script and the
+/// fields it doesn't have.
+fn set_fields(script: &str, values: &[&Field]) -> (String, Vec<String>) {
+    let (fields, _) = fields(script);
+
+    let line_ending = if script.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut lines = script.lines().map(str::to_string).collect::<Vec<_>>();
+    let mut missing = Vec::new();
+
+    for field in values {
+        let Some(target) = get(&fields, &field.name) else {
+            missing.push(field.name.clone());
+            continue;
+        };
+
+        // A value on a line of its own can't be set without reformatting the script.
+        let line = &mut lines[target.line];
+        match value_token(line) {
+            Some((range, _)) => line.replace_range(range, &format!("\"{}\"", field.value)),
+            None => missing.push(field.name.clone()),
+        }
+    }
+
+    let mut s = lines.join(line_ending);
+    if script.ends_with('\n') {
+        s.push_str(line_ending);
+    }
+
+    (s, missing)
+}
+
+/// Re-applies the fields of `user` that differ from `base` to `new`, `base` being the stock
+/// script `user` was edited from and `new` an updated stock script.
+pub fn rebase_script(base: &str, user: &str, new: &str) -> (String, Rebased) {
+    let (base_fields, base_block) = fields(base);
+    let (user_fields, _) = fields(user);
+    let (new_fields, new_block) = fields(new);
+
+    let edited = user_fields
+        .iter()
+        .filter(|f| get(&base_fields, &f.name).map(|b| &b.value) != Some(&f.value))
+        .collect::<Vec<_>>();
+
+    if edited.is_empty() {
+        return (new.to_string(), Rebased::Stock);
+    }
+
+    let (script, missing) = set_fields(new, &edited);
+
+    let mut conflicts = missing
+        .iter()
+        .map(|name| format!("`{}` is gone", name))
+        .collect::<Vec<_>>();
+
+    for field in edited.iter().filter(|f| !f.name.starts_with("crosshair.")) {
+        let upstream = get(&base_fields, &field.name).map(|b| &b.value);
+
+        if get(&new_fields, &field.name).is_some_and(|n| upstream != Some(&n.value)) {
+            conflicts.push(format!("`{}` changed upstream", field.name));
+        }
+    }
+
+    if edited.iter().any(|f| f.name.starts_with("crosshair.")) && base_block != new_block {
+        conflicts.push("the crosshair block changed upstream".to_string());
+    }
+
+    let names = edited.iter().map(|f| f.name.clone()).collect();
+
+    if conflicts.is_empty() {
+        (script, Rebased::Edited(names))
+    } else {
+        (script, Rebased::Conflict(names, conflicts.join(", ")))
+    }
+}
+
+/// Copies the crosshair and explosion values of `ours` into `theirs`, another mod's copy of the
+/// same weapon script. Returns the merged script and the fields `theirs` doesn't have.
+pub fn merge(ours: &str, theirs: &str) -> (String, Vec<String>) {
+    let (ours, _) = fields(ours);
+
+    set_fields(theirs, &ours.iter().collect::<Vec<_>>())
+}
+
+fn read(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    fs::read_to_string(path)
+        .map(Some)
+        .with_context(|| format!("Failed to open {}", path.display()))
+}
+
+/// Rebases every script of `user_dir` onto `new_dir`, `base_dir` holding the stock scripts they
+/// were edited from. Rebased scripts are written back to `user_dir` unless `dry_run`.
+pub fn rebase(
+    base_dir: &Path,
+    user_dir: &Path,
+    new_dir: &Path,
+    dry_run: bool,
+) -> Result<Vec<(String, Rebased)>> {
+    let mut names = Vec::new();
+
+    for dir in [user_dir, new_dir] {
+        for file in dir
+            .read_dir()
+            .with_context(|| format!("Failed to read folder `{}`", dir.display()))?
+        {
+            let path = file?.path();
+
+            if path.extension() == Some("txt".as_ref()) {
+                names.push(path.file_name().unwrap().to_string_lossy().into_owned());
+            }
+        }
+    }
+
+    names.sort();
+    names.dedup();
+
+    if names.is_empty() {
+        bail!("No weapon scripts to rebase");
+    }
+
+    let mut report = Vec::new();
+
+    for name in names {
+        let user_path = user_dir.join(&name);
+
+        let new = match read(&new_dir.join(&name))? {
+            Some(s) => s,
+            None => {
+                report.push((name, Rebased::Removed));
+                continue;
+            }
+        };
+
+        let (script, rebased) = match read(&user_path)? {
+            Some(user) => {
+                // Without the script it came from, everything that differs counts as an edit.
+                let base = read(&base_dir.join(&name))?.unwrap_or_else(|| new.clone());
+                rebase_script(&base, &user, &new)
+            }
+            None => (new, Rebased::Added),
+        };
+
+        if !dry_run {
+            fs::write(&user_path, script)
+                .with_context(|| format!("Failed to write {}", user_path.display()))?;
+        }
+
+        report.push((name, rebased));
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BASE: &str = "\
+WeaponData
+{
+\t\"ExplosionEffect\"\t\"ExplosionCore_wall\"
+\tTextureData
+\t{
+\t\t\"crosshair\"
+\t\t{
+\t\t\t\t\"file\"\t\t\"sprites/crosshairs\"
+\t\t\t\t\"x\"\t\t\"32\"
+\t\t\t\t\"y\"\t\t\"32\"
+\t\t\t\t\"width\"\t\t\"32\"
+\t\t\t\t\"height\"\t\"32\"
+\t\t}
+\t}
+}
+";
+
+    fn edit(script: &str, from: &str, to: &str) -> String {
+        script.replacen(from, to, 1)
+    }
+
+    #[test]
+    fn rebase_script() {
+        let user = edit(
+            &edit(BASE, "sprites/crosshairs", "vgui/replay/thumbnails/dot"),
+            "\"x\"\t\t\"32\"",
+            "\"x\"\t\t\"0\"",
+        );
+        // Valve adds a field outside of what the tool edits.
+        let new = edit(
+            BASE,
+            "WeaponData\n{\n",
+            "WeaponData\n{\n\t\"Damage\"\t\"90\"\n",
+        );
+
+        let (script, rebased) = super::rebase_script(BASE, &user, &new);
+
+        assert_eq!(
+            rebased,
+            Rebased::Edited(vec!["crosshair.file".into(), "crosshair.x".into()])
+        );
+        assert_eq!(
+            script,
+            edit(
+                &user,
+                "WeaponData\n{\n",
+                "WeaponData\n{\n\t\"Damage\"\t\"90\"\n"
+            )
+        );
+
+        assert_eq!(
+            super::rebase_script(BASE, BASE, &new),
+            (new.clone(), Rebased::Stock)
+        );
+    }
+
+    #[test]
+    fn conflicts() {
+        let user = edit(BASE, "sprites/crosshairs", "vgui/replay/thumbnails/dot");
+        let user = edit(&user, "ExplosionCore_wall", "electrocuted_red_flash");
+
+        let new = edit(BASE, "\"width\"\t\t\"32\"", "\"width\"\t\t\"48\"");
+        let new = edit(&new, "ExplosionCore_wall", "ExplosionCore_buildings");
+
+        let (script, rebased) = super::rebase_script(BASE, &user, &new);
+
+        assert_eq!(
+            rebased,
+            Rebased::Conflict(
+                vec!["ExplosionEffect".into(), "crosshair.file".into()],
+                "`ExplosionEffect` changed upstream, the crosshair block changed upstream".into()
+            )
+        );
+        assert!(script.contains("vgui/replay/thumbnails/dot"));
+        assert!(script.contains("\"48\""));
+        assert!(script.contains("electrocuted_red_flash"));
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(value("\t\"x\"\t\t\"32\""), Some("32".into()));
+        assert_eq!(
+            value("\"file\" \"my crosshairs/dot\""),
+            Some("my crosshairs/dot".into())
+        );
+        assert_eq!(value("x 32 // comment"), Some("32".into()));
+        assert_eq!(value("\"x\"\t// comment"), None);
+
+        // An empty value is replaced, not appended to.
+        let base = edit(BASE, "ExplosionCore_wall", "");
+        let user = edit(BASE, "ExplosionCore_wall", "electrocuted_red_flash");
+
+        let (script, _) = super::merge(&user, &base);
+        assert!(script.contains("\t\"ExplosionEffect\"\t\"electrocuted_red_flash\"\n"));
+
+        let (script, _) = super::merge(&base, &user);
+        assert!(script.contains("\t\"ExplosionEffect\"\t\"\"\n"));
+    }
+
+    #[test]
+    fn merge() {
+        let ours = edit(BASE, "sprites/crosshairs", "vgui/replay/thumbnails/dot");
+        // Another mod's copy, with its own changes and without the explosion.
+        let theirs = edit(BASE, "\t\"ExplosionEffect\"\t\"ExplosionCore_wall\"\n", "")
+            .replace("\"32\"", "\"24\"");
+
+        let (script, missing) = super::merge(&ours, &theirs);
+
+        assert_eq!(missing, ["ExplosionEffect"]);
+        assert_eq!(
+            script,
+            edit(&ours, "\t\"ExplosionEffect\"\t\"ExplosionCore_wall\"\n", "")
+        );
+    }
+
+    #[test]
+    fn rebase() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let [base, user, new] = ["base", "user", "new"].map(|d| temp_dir.path().join(d));
+
+        for dir in [&base, &user, &new] {
+            fs::create_dir(dir).unwrap();
+        }
+
+        fs::write(base.join("tf_weapon_bat.txt"), BASE).unwrap();
+        fs::write(
+            user.join("tf_weapon_bat.txt"),
+            edit(BASE, "sprites/crosshairs", "vgui/replay/thumbnails/dot"),
+        )
+        .unwrap();
+        fs::write(user.join("tf_weapon_old.txt"), BASE).unwrap();
+        fs::write(new.join("tf_weapon_bat.txt"), BASE.replace('\n', "\r\n")).unwrap();
+        fs::write(new.join("tf_weapon_new.txt"), BASE).unwrap();
+
+        let report = super::rebase(&base, &user, &new, false).unwrap();
+
+        assert_eq!(
+            report,
+            [
+                (
+                    "tf_weapon_bat.txt".to_string(),
+                    Rebased::Edited(vec!["crosshair.file".into()])
+                ),
+                ("tf_weapon_new.txt".to_string(), Rebased::Added),
+                ("tf_weapon_old.txt".to_string(), Rebased::Removed),
+            ]
+        );
+
+        let bat = fs::read_to_string(user.join("tf_weapon_bat.txt")).unwrap();
+        assert!(bat.contains("\"vgui/replay/thumbnails/dot\"\r\n"));
+        assert!(user.join("tf_weapon_new.txt").exists());
+    }
+}
@@ -0,0 +1,580 @@
+use crate::manifest;
+use crate::model::{self, CrosshairItem, Weapon};
+use crate::texture::TextureInfo;
+use crate::ExplosionEffect;
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// Name of the loadout kept next to the binary.
+const FILE: &str = "loadout.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Is,
+    IsNot,
+    In,
+    NotIn,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// Operators as written, longer ones first so `is not` isn't read as `is`.
+const OPS: [(&str, Op); 11] = [
+    ("is not", Op::IsNot),
+    ("not in", Op::NotIn),
+    ("is", Op::Is),
+    ("in", Op::In),
+    ("!=", Op::IsNot),
+    ("==", Op::Is),
+    ("<=", Op::LessOrEqual),
+    (">=", Op::GreaterOrEqual),
+    ("=", Op::Is),
+    ("<", Op::Less),
+    (">", Op::Greater),
+];
+
+/// A test of one field of a weapon, like `ProjectileType is projectile_bullet`. Fields are
+/// `class`, `slot` (its number or name), `script`, or any key of the weapon script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    field: String,
+    op: Op,
+    values: Vec<String>,
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+/// Numbers compare as numbers, so `1` is `1.0`, and anything else ignoring case.
+fn equal(a: &str, b: &str) -> bool {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+impl Condition {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+
+        let end = s
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(s.len());
+        let (field, rest) = s.split_at(end);
+
+        if field.is_empty() {
+            bail!("Expected a field in `{}`", s);
+        }
+
+        let rest = rest.trim_start();
+        let lower = rest.to_ascii_lowercase();
+
+        let (op_str, op) = OPS
+            .into_iter()
+            .find(|(o, _)| {
+                // Word operators need a space or a list after them, or `isabel` would be `is`.
+                lower.starts_with(o)
+                    && (!o.ends_with(char::is_alphabetic)
+                        || lower[o.len()..].starts_with([' ', '[']))
+            })
+            .ok_or_else(|| anyhow!("Expected an operator after `{}`", field))?;
+
+        let value = rest[op_str.len()..].trim();
+
+        let values = match op {
+            Op::In | Op::NotIn => {
+                let list = value
+                    .strip_prefix('[')
+                    .and_then(|v| v.strip_suffix(']'))
+                    .unwrap_or(value);
+
+                list.split(',')
+                    .map(unquote)
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            _ => vec![unquote(value).to_string()],
+        };
+
+        if values.iter().all(String::is_empty) {
+            bail!("Expected a value after `{} {}`", field, op_str);
+        }
+
+        if matches!(
+            op,
+            Op::Less | Op::LessOrEqual | Op::Greater | Op::GreaterOrEqual
+        ) && values[0].parse::<f64>().is_err()
+        {
+            bail!("`{}` isn't a number", values[0]);
+        }
+
+        Ok(Self {
+            field: field.to_string(),
+            op,
+            values,
+        })
+    }
+
+    /// The values `weapon` has for the field, empty when it has none.
+    fn field_values(&self, weapon: &Weapon) -> Vec<String> {
+        let association = weapon.association.as_ref();
+
+        match self.field.to_ascii_lowercase().as_str() {
+            "class" => association
+                .map(|a| vec![a.class.to_str().to_string()])
+                .unwrap_or_default(),
+            "slot" => association
+                .map(|a| vec![a.slot.to_u8().to_string(), a.slot.to_str().to_string()])
+                .unwrap_or_default(),
+            "script" => vec![weapon.file.name.clone()],
+            _ => weapon
+                .file
+                .data
+                .value(&self.field)
+                .map(|v| vec![v.to_string()])
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Whether `weapon` passes. Weapons without the field only pass `is not` and `not in`.
+    pub fn matches(&self, weapon: &Weapon) -> bool {
+        let actual = self.field_values(weapon);
+        let any_equal =
+            |values: &[String]| actual.iter().any(|a| values.iter().any(|v| equal(a, v)));
+        let compare = || {
+            let a = actual.first()?.parse::<f64>().ok()?;
+            let v = self.values[0].parse::<f64>().ok()?;
+            a.partial_cmp(&v)
+        };
+
+        match self.op {
+            Op::Is => any_equal(&self.values[..1]),
+            Op::IsNot => !any_equal(&self.values[..1]),
+            Op::In => any_equal(&self.values),
+            Op::NotIn => !any_equal(&self.values),
+            Op::Less => compare().is_some_and(|o| o.is_lt()),
+            Op::LessOrEqual => compare().is_some_and(|o| o.is_le()),
+            Op::Greater => compare().is_some_and(|o| o.is_gt()),
+            Op::GreaterOrEqual => compare().is_some_and(|o| o.is_ge()),
+        }
+    }
+}
+
+/// Splits `s` on `and`, ignoring case.
+fn split_and(s: &str) -> Vec<&str> {
+    let lower = s.to_ascii_lowercase();
+    let mut parts = Vec::new();
+    let mut start = 0;
+
+    for (i, _) in lower.match_indices(" and ") {
+        parts.push(&s[start..i]);
+        start = i + " and ".len();
+    }
+    parts.push(&s[start..]);
+
+    parts
+}
+
+/// A crosshair and explosion to give every weapon matching all of `when`, a list of
+/// conditions joined by `and`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub when: String,
+    conditions: Vec<Condition>,
+    /// Name of the crosshair, without its extension.
+    pub crosshair: Option<String>,
+    pub explosion: Option<ExplosionEffect>,
+}
+
+impl Rule {
+    pub fn new(
+        when: &str,
+        crosshair: Option<String>,
+        explosion: Option<ExplosionEffect>,
+    ) -> Result<Self> {
+        if crosshair.is_none() && explosion.is_none() {
+            bail!("Expected a crosshair or explosion for `{}`", when);
+        }
+
+        let conditions = split_and(when)
+            .into_iter()
+            .map(Condition::parse)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Invalid rule `{}`", when))?;
+
+        Ok(Self {
+            when: when.trim().to_string(),
+            conditions,
+            crosshair: crosshair.map(|c| model::crosshair_stem(&c).to_string()),
+            explosion,
+        })
+    }
+
+    pub fn matches(&self, weapon: &Weapon) -> bool {
+        self.conditions.iter().all(|c| c.matches(weapon))
+    }
+
+    fn from_json(value: &json::JsonValue) -> Result<Self> {
+        let when = value["when"]
+            .as_str()
+            .ok_or_else(|| anyhow!("`when` isn't a string"))?;
+
+        Self::new(
+            when,
+            value["crosshair"].as_str().map(str::to_string),
+            value["explosion"].as_str().map(ExplosionEffect::from),
+        )
+    }
+
+    fn to_json(&self) -> json::JsonValue {
+        let mut value = json::JsonValue::new_object();
+
+        value["when"] = self.when.as_str().into();
+
+        if let Some(crosshair) = &self.crosshair {
+            value["crosshair"] = crosshair.as_str().into();
+        }
+
+        if let Some(explosion) = &self.explosion {
+            value["explosion"] = explosion.to_str().into();
+        }
+
+        value
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.when)?;
+
+        if let Some(crosshair) = &self.crosshair {
+            write!(f, " crosshair {}", crosshair)?;
+        }
+
+        if let Some(explosion) = &self.explosion {
+            write!(f, " explosion {}", explosion.to_str())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What the rules give a weapon, left out where it already has it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assignment {
+    pub weapon: usize,
+    pub crosshair: Option<String>,
+    pub explosion: Option<ExplosionEffect>,
+}
+
+/// Rules saved for reapplying after scripts are reset or updated.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Loadout {
+    pub rules: Vec<Rule>,
+}
+
+impl Loadout {
+    pub fn path() -> Result<PathBuf> {
+        Ok(crate::exe_dir()?.join(FILE))
+    }
+
+    /// The loadout next to the binary, empty if there's none yet.
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::read(&path)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.write(&Self::path()?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        let s =
+            fs::read_to_string(path).with_context(|| format!("Failed to open {}", file_name))?;
+
+        Self::parse(&s).with_context(|| format!("Failed to parse {}", file_name))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        let json = json::parse(s)?;
+
+        if !json["rules"].is_array() {
+            bail!("Expected an array of rules");
+        }
+
+        let rules = json["rules"]
+            .members()
+            .enumerate()
+            .map(|(i, r)| Rule::from_json(r).with_context(|| format!("Invalid rule {}", i + 1)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { rules })
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = json::JsonValue::new_object();
+
+        json["rules"] = json::JsonValue::Array(self.rules.iter().map(Rule::to_json).collect());
+
+        json::stringify_pretty(json, 4)
+    }
+
+    /// What the rules change about `weapons`, later rules winning over earlier ones.
+    /// Explosions only go to weapons that have them.
+    pub fn assignments(&self, weapons: &[Weapon]) -> Vec<Assignment> {
+        let mut assignments = Vec::new();
+
+        for (i, weapon) in weapons.iter().enumerate() {
+            let mut crosshair = None;
+            let mut explosion = None;
+
+            for rule in self.rules.iter().filter(|r| r.matches(weapon)) {
+                if rule.crosshair.is_some() {
+                    crosshair = rule.crosshair.clone();
+                }
+
+                if rule.explosion.is_some() && weapon.uses_explosion() {
+                    explosion = rule.explosion.clone();
+                }
+            }
+
+            let crosshair = crosshair.filter(|c| {
+                model::material_key(&weapon.file.crosshair) != model::crosshair_material(c)
+            });
+            let explosion = explosion.filter(|e| weapon.file.explosion_effect.as_ref() != Some(e));
+
+            if crosshair.is_some() || explosion.is_some() {
+                assignments.push(Assignment {
+                    weapon: i,
+                    crosshair,
+                    explosion,
+                });
+            }
+        }
+
+        assignments
+    }
+}
+
+/// Writes `assignments` into the scripts of `weapons`, with crosshairs from `crosshair_dir`.
+/// Returns a line per change, and the errors of weapons left as they were.
+pub fn apply(
+    weapons: &[Weapon],
+    assignments: &[Assignment],
+    crosshair_dir: &Path,
+) -> (Vec<String>, Vec<String>) {
+    let mut changes = Vec::new();
+    let mut errors = Vec::new();
+
+    for a in assignments {
+        let weapon = &weapons[a.weapon];
+
+        if let Err(e) = apply_one(weapon, a, crosshair_dir, &mut changes) {
+            errors.push(format!("Skipping {}; {:#}", weapon.file.name, e));
+        }
+    }
+
+    (changes, errors)
+}
+
+fn apply_one(
+    weapon: &Weapon,
+    assignment: &Assignment,
+    crosshair_dir: &Path,
+    changes: &mut Vec<String>,
+) -> Result<()> {
+    let file = &weapon.file;
+
+    if let Some(name) = &assignment.crosshair {
+        let path = crosshair_dir.join(name).with_extension("vtf");
+
+        if !path.exists() {
+            bail!("Crosshair `{}` doesn't exist", name);
+        }
+
+        // Unreadable textures get the default size, as in the crosshair list.
+        let size = TextureInfo::open(&path)
+            .map(|t| (t.header.width as i32, t.header.height as i32))
+            .unwrap_or_default();
+
+        let crosshair = CrosshairItem {
+            name: format!("{}.vtf", name),
+            path,
+            size,
+        };
+
+        fs::write(&file.path, file.replace_crosshair(&crosshair)?)
+            .with_context(|| format!("Failed to write {}", file.path.display()))?;
+        manifest::record_write(&file.path, false)?;
+
+        changes.push(format!(
+            "{}: {} -> {}",
+            file.name,
+            weapon.crosshair_file_name(),
+            name
+        ));
+    }
+
+    if let Some(explosion) = &assignment.explosion {
+        fs::write(&file.path, file.replace_explosion(explosion)?)
+            .with_context(|| format!("Failed to write {}", file.path.display()))?;
+        manifest::record_write(&file.path, false)?;
+
+        changes.push(format!(
+            "{}: {} -> {}",
+            file.name,
+            file.explosion_effect
+                .as_ref()
+                .map_or("None", ExplosionEffect::to_str),
+            explosion.to_str()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::associations::Associations;
+
+    fn weapons() -> Vec<Weapon> {
+        let (weapons, _) = model::load_weapons(
+            Path::new("resources/scripts"),
+            &Associations::builtin().unwrap(),
+        )
+        .unwrap();
+
+        weapons
+    }
+
+    fn matching(weapons: &[Weapon], when: &str) -> Vec<String> {
+        let rule = Rule::new(when, Some("dot".into()), None).unwrap();
+
+        weapons
+            .iter()
+            .filter(|w| rule.matches(w))
+            .map(|w| w.file.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn conditions() {
+        let weapons = weapons();
+
+        let hitscan = matching(&weapons, "ProjectileType is projectile_bullet");
+        assert!(hitscan.contains(&"tf_weapon_scattergun".to_string()));
+        assert!(!hitscan.contains(&"tf_weapon_rocketlauncher".to_string()));
+
+        let melee = matching(&weapons, "MeleeWeapon = 1");
+        assert!(melee.contains(&"tf_weapon_knife".to_string()));
+        assert!(!melee.contains(&"tf_weapon_smg".to_string()));
+
+        assert_eq!(
+            matching(
+                &weapons,
+                "slot = 2 AND class in [Scout, Sniper] and Damage > 7"
+            ),
+            [
+                "tf_weapon_pistol_scout",
+                "tf_weapon_handgun_scout_secondary",
+                "tf_weapon_smg",
+                "tf_weapon_charged_smg"
+            ]
+        );
+        assert_eq!(
+            matching(&weapons, "slot is Melee and class = spy"),
+            ["tf_weapon_knife"]
+        );
+        // Weapons without a key pass `is not`.
+        assert!(matching(&weapons, "MeleeWeapon != 1").contains(&"tf_weapon_smg".to_string()));
+
+        for invalid in [
+            "",
+            "Damage",
+            "Damage >",
+            "Damage > lots",
+            "= 1",
+            "class in []",
+        ] {
+            assert!(
+                Rule::new(invalid, Some("dot".into()), None).is_err(),
+                "{}",
+                invalid
+            );
+        }
+        assert!(Rule::new("class is Spy", None, None).is_err());
+    }
+
+    #[test]
+    fn assignments() {
+        let weapons = weapons();
+        let loadout = Loadout {
+            rules: vec![
+                Rule::new(
+                    "ProjectileType is projectile_bullet",
+                    Some("dot".into()),
+                    None,
+                )
+                .unwrap(),
+                Rule::new(
+                    "class = Soldier",
+                    Some("circle.vtf".into()),
+                    Some(ExplosionEffect::ElectricShock),
+                )
+                .unwrap(),
+                Rule::new("script is tf_weapon_smg", Some("cross".into()), None).unwrap(),
+            ],
+        };
+
+        let assignments = loadout.assignments(&weapons);
+        let get = |name: &str| {
+            assignments
+                .iter()
+                .find(|a| weapons[a.weapon].file.name == name)
+        };
+
+        assert_eq!(
+            get("tf_weapon_scattergun").unwrap().crosshair.as_deref(),
+            Some("dot")
+        );
+        assert_eq!(
+            get("tf_weapon_smg").unwrap().crosshair.as_deref(),
+            Some("cross")
+        );
+
+        let rocket_launcher = get("tf_weapon_rocketlauncher").unwrap();
+        assert_eq!(rocket_launcher.crosshair.as_deref(), Some("circle"));
+        assert_eq!(
+            rocket_launcher.explosion,
+            Some(ExplosionEffect::ElectricShock)
+        );
+        assert_eq!(get("tf_weapon_shovel").unwrap().explosion, None);
+        assert!(get("tf_weapon_knife").is_none());
+
+        assert_eq!(Loadout::parse(&loadout.to_json()).unwrap(), loadout);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::preview::{self, Background};
+use crate::CrosshairRect;
+
+use image::{imageops, FilterType, RgbaImage};
+
+/// Resolutions offered for simulating, as width and height.
+pub const RESOLUTIONS: [(u32, u32); 10] = [
+    (1024, 768),
+    (1280, 720),
+    (1280, 1024),
+    (1366, 768),
+    (1600, 900),
+    (1680, 1050),
+    (1920, 1080),
+    (1920, 1200),
+    (2560, 1440),
+    (3840, 2160),
+];
+
+/// The settings the game draws a crosshair with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    pub resolution: (u32, u32),
+    /// `cl_crosshair_scale`, where 32 draws the crosshair at the size of its weapon script.
+    pub scale: f32,
+    /// `cl_crosshair_red`, `cl_crosshair_green` and `cl_crosshair_blue`.
+    pub color: [u8; 3],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            resolution: (1920, 1080),
+            scale: 32.0,
+            color: [200, 200, 200],
+        }
+    }
+}
+
+/// Size of the crosshair on screen. Like the HUD, this doesn't depend on the resolution.
+pub fn screen_size(rect: CrosshairRect, settings: &Settings) -> (u32, u32) {
+    let scale = settings.scale / 32.0;
+
+    (
+        (rect.width as f32 * scale + 0.5).max(0.0) as u32,
+        (rect.height as f32 * scale + 0.5).max(0.0) as u32,
+    )
+}
+
+/// A screen of `settings.resolution` with the `rect` part of `texture` drawn at its centre, the
+/// way the HUD draws a weapon's crosshair.
+pub fn render(
+    texture: &RgbaImage,
+    rect: CrosshairRect,
+    settings: &Settings,
+    background: &Background,
+) -> RgbaImage {
+    let (width, height) = settings.resolution;
+
+    let background = match background {
+        Background::Screenshot(screenshot) => Background::Screenshot(imageops::resize(
+            screenshot,
+            width,
+            height,
+            FilterType::Triangle,
+        )),
+        b => b.clone(),
+    };
+
+    let mut screen = RgbaImage::from_fn(width, height, |x, y| {
+        let [r, g, b] = background.pixel(x, y, width, height);
+        image::Rgba([r, g, b, 255])
+    });
+
+    let x = rect.x.clamp(0, texture.width() as i32) as u32;
+    let y = rect.y.clamp(0, texture.height() as i32) as u32;
+    let cropped = RgbaImage::from_fn(
+        (rect.width.max(0) as u32).min(texture.width() - x),
+        (rect.height.max(0) as u32).min(texture.height() - y),
+        |cx, cy| *texture.get_pixel(x + cx, y + cy),
+    );
+
+    let (crosshair_width, crosshair_height) = screen_size(rect, settings);
+
+    if cropped.width() == 0 || crosshair_width == 0 || crosshair_height == 0 {
+        return screen;
+    }
+
+    let crosshair = imageops::resize(
+        &cropped,
+        crosshair_width,
+        crosshair_height,
+        FilterType::Triangle,
+    );
+
+    let left = (width / 2) as i64 - (crosshair_width / 2) as i64;
+    let top = (height / 2) as i64 - (crosshair_height / 2) as i64;
+
+    for (cx, cy, pixel) in crosshair.enumerate_pixels() {
+        let (sx, sy) = (left + cx as i64, top + cy as i64);
+
+        if sx < 0 || sy < 0 || sx >= width as i64 || sy >= height as i64 {
+            continue;
+        }
+
+        let mut tinted = *pixel;
+        for (channel, tint) in tinted.0.iter_mut().zip(settings.color) {
+            *channel = (*channel as u32 * tint as u32 / 255) as u8;
+        }
+
+        let below = screen.get_pixel(sx as u32, sy as u32).0;
+        screen.put_pixel(
+            sx as u32,
+            sy as u32,
+            preview::blend([below[0], below[1], below[2]], tinted),
+        );
+    }
+
+    screen
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render() {
+        // A white 4x4 square in the bottom right of an 8x8 texture.
+        let texture = RgbaImage::from_fn(8, 8, |x, y| {
+            if x >= 4 && y >= 4 {
+                image::Rgba([255; 4])
+            } else {
+                image::Rgba([0; 4])
+            }
+        });
+
+        let rect = CrosshairRect {
+            x: 4,
+            y: 4,
+            width: 4,
+            height: 4,
+        };
+
+        let settings = Settings {
+            resolution: (20, 10),
+            scale: 64.0,
+            color: [255, 0, 0],
+        };
+
+        assert_eq!(screen_size(rect, &settings), (8, 8));
+
+        let screen = super::render(&texture, rect, &settings, &Background::Black);
+
+        assert_eq!(screen.dimensions(), (20, 10));
+        assert_eq!(screen.get_pixel(6, 1).0, [255, 0, 0, 255]);
+        assert_eq!(screen.get_pixel(13, 8).0, [255, 0, 0, 255]);
+        assert_eq!(screen.get_pixel(5, 1).0, [0, 0, 0, 255]);
+        assert_eq!(screen.get_pixel(14, 8).0, [0, 0, 0, 255]);
+        assert_eq!(screen.get_pixel(10, 0).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,1062 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// Pixel formats of a VTF, in the order of their numeric value in the header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Rgba8888,
+    Abgr8888,
+    Rgb888,
+    Bgr888,
+    Rgb565,
+    I8,
+    Ia88,
+    P8,
+    A8,
+    Rgb888Bluescreen,
+    Bgr888Bluescreen,
+    Argb8888,
+    Bgra8888,
+    Dxt1,
+    Dxt3,
+    Dxt5,
+    Bgrx8888,
+    Bgr565,
+    Bgrx5551,
+    Bgra4444,
+    Dxt1OneBitAlpha,
+    Bgra5551,
+    Uv88,
+    Uvwq8888,
+    Rgba16161616F,
+    Rgba16161616,
+    Uvlx8888,
+    None,
+}
+
+impl Format {
+    const ALL: [Format; 27] = [
+        Self::Rgba8888,
+        Self::Abgr8888,
+        Self::Rgb888,
+        Self::Bgr888,
+        Self::Rgb565,
+        Self::I8,
+        Self::Ia88,
+        Self::P8,
+        Self::A8,
+        Self::Rgb888Bluescreen,
+        Self::Bgr888Bluescreen,
+        Self::Argb8888,
+        Self::Bgra8888,
+        Self::Dxt1,
+        Self::Dxt3,
+        Self::Dxt5,
+        Self::Bgrx8888,
+        Self::Bgr565,
+        Self::Bgrx5551,
+        Self::Bgra4444,
+        Self::Dxt1OneBitAlpha,
+        Self::Bgra5551,
+        Self::Uv88,
+        Self::Uvwq8888,
+        Self::Rgba16161616F,
+        Self::Rgba16161616,
+        Self::Uvlx8888,
+    ];
+
+    fn from_i32(n: i32) -> Result<Self> {
+        if n == -1 {
+            return Ok(Self::None);
+        }
+
+        usize::try_from(n)
+            .ok()
+            .and_then(|n| Self::ALL.get(n).copied())
+            .ok_or_else(|| anyhow!("Unknown image format `{}`", n))
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Rgba8888 => "RGBA8888",
+            Self::Abgr8888 => "ABGR8888",
+            Self::Rgb888 => "RGB888",
+            Self::Bgr888 => "BGR888",
+            Self::Rgb565 => "RGB565",
+            Self::I8 => "I8",
+            Self::Ia88 => "IA88",
+            Self::P8 => "P8",
+            Self::A8 => "A8",
+            Self::Rgb888Bluescreen => "RGB888 bluescreen",
+            Self::Bgr888Bluescreen => "BGR888 bluescreen",
+            Self::Argb8888 => "ARGB8888",
+            Self::Bgra8888 => "BGRA8888",
+            Self::Dxt1 => "DXT1",
+            Self::Dxt3 => "DXT3",
+            Self::Dxt5 => "DXT5",
+            Self::Bgrx8888 => "BGRX8888",
+            Self::Bgr565 => "BGR565",
+            Self::Bgrx5551 => "BGRX5551",
+            Self::Bgra4444 => "BGRA4444",
+            Self::Dxt1OneBitAlpha => "DXT1 one-bit alpha",
+            Self::Bgra5551 => "BGRA5551",
+            Self::Uv88 => "UV88",
+            Self::Uvwq8888 => "UVWQ8888",
+            Self::Rgba16161616F => "RGBA16161616F",
+            Self::Rgba16161616 => "RGBA16161616",
+            Self::Uvlx8888 => "UVLX8888",
+            Self::None => "None",
+        }
+    }
+
+    /// Whether the format can store anything but a fully opaque alpha.
+    pub fn has_alpha(self) -> bool {
+        matches!(
+            self,
+            Self::Rgba8888
+                | Self::Abgr8888
+                | Self::Ia88
+                | Self::A8
+                | Self::Argb8888
+                | Self::Bgra8888
+                | Self::Dxt1OneBitAlpha
+                | Self::Dxt3
+                | Self::Dxt5
+                | Self::Bgra4444
+                | Self::Bgra5551
+                | Self::Rgba16161616F
+                | Self::Rgba16161616
+        )
+    }
+
+    /// Size in bytes of a `width` by `height` image, if the format can be decoded.
+    pub fn data_size(self, width: usize, height: usize) -> Option<usize> {
+        let bytes_per_pixel = match self {
+            Self::Dxt1 | Self::Dxt1OneBitAlpha => {
+                return Some(width.div_ceil(4) * height.div_ceil(4) * 8)
+            }
+            Self::Dxt3 | Self::Dxt5 => return Some(width.div_ceil(4) * height.div_ceil(4) * 16),
+            Self::I8 | Self::A8 => 1,
+            Self::Ia88
+            | Self::Rgb565
+            | Self::Bgr565
+            | Self::Bgrx5551
+            | Self::Bgra5551
+            | Self::Bgra4444
+            | Self::Uv88 => 2,
+            Self::Rgb888 | Self::Bgr888 | Self::Rgb888Bluescreen | Self::Bgr888Bluescreen => 3,
+            Self::Rgba8888
+            | Self::Abgr8888
+            | Self::Argb8888
+            | Self::Bgra8888
+            | Self::Bgrx8888
+            | Self::Uvwq8888
+            | Self::Uvlx8888 => 4,
+            Self::Rgba16161616F | Self::Rgba16161616 => 8,
+            Self::P8 | Self::None => return None,
+        };
+
+        Some(width * height * bytes_per_pixel)
+    }
+
+    pub fn is_compressed(self) -> bool {
+        matches!(
+            self,
+            Self::Dxt1 | Self::Dxt1OneBitAlpha | Self::Dxt3 | Self::Dxt5
+        )
+    }
+}
+
+pub mod flags {
+    pub const POINT_SAMPLE: u32 = 0x1;
+    pub const TRILINEAR: u32 = 0x2;
+    pub const CLAMP_S: u32 = 0x4;
+    pub const CLAMP_T: u32 = 0x8;
+    pub const ANISOTROPIC: u32 = 0x10;
+    pub const HINT_DXT5: u32 = 0x20;
+    pub const SRGB: u32 = 0x40;
+    pub const NORMAL: u32 = 0x80;
+    pub const NO_MIP: u32 = 0x100;
+    pub const NO_LOD: u32 = 0x200;
+    pub const ALL_MIPS: u32 = 0x400;
+    pub const PROCEDURAL: u32 = 0x800;
+    pub const ONE_BIT_ALPHA: u32 = 0x1000;
+    pub const EIGHT_BIT_ALPHA: u32 = 0x2000;
+    pub const ENVMAP: u32 = 0x4000;
+    pub const RENDER_TARGET: u32 = 0x8000;
+    pub const DEPTH_RENDER_TARGET: u32 = 0x10000;
+    pub const NO_DEBUG_OVERRIDE: u32 = 0x20000;
+    pub const SINGLE_COPY: u32 = 0x40000;
+    pub const NO_DEPTH_BUFFER: u32 = 0x800000;
+    pub const CLAMP_U: u32 = 0x2000000;
+    pub const VERTEX_TEXTURE: u32 = 0x4000000;
+    pub const SS_BUMP: u32 = 0x8000000;
+    pub const BORDER: u32 = 0x20000000;
+
+    pub const NAMES: [(u32, &str); 24] = [
+        (POINT_SAMPLE, "Point sample"),
+        (TRILINEAR, "Trilinear"),
+        (CLAMP_S, "Clamp S"),
+        (CLAMP_T, "Clamp T"),
+        (ANISOTROPIC, "Anisotropic"),
+        (HINT_DXT5, "Hint DXT5"),
+        (SRGB, "sRGB"),
+        (NORMAL, "Normal map"),
+        (NO_MIP, "No mip"),
+        (NO_LOD, "No LOD"),
+        (ALL_MIPS, "All mips"),
+        (PROCEDURAL, "Procedural"),
+        (ONE_BIT_ALPHA, "One-bit alpha"),
+        (EIGHT_BIT_ALPHA, "Eight-bit alpha"),
+        (ENVMAP, "Environment map"),
+        (RENDER_TARGET, "Render target"),
+        (DEPTH_RENDER_TARGET, "Depth render target"),
+        (NO_DEBUG_OVERRIDE, "No debug override"),
+        (SINGLE_COPY, "Single copy"),
+        (NO_DEPTH_BUFFER, "No depth buffer"),
+        (CLAMP_U, "Clamp U"),
+        (VERTEX_TEXTURE, "Vertex texture"),
+        (SS_BUMP, "SS bump"),
+        (BORDER, "Border"),
+    ];
+
+    pub fn names(flags: u32) -> Vec<&'static str> {
+        NAMES
+            .iter()
+            .filter(|(f, _)| flags & f != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// Entry of the resource directory of a 7.3+ VTF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resource {
+    pub tag: [u8; 3],
+    pub flags: u8,
+    /// Offset of the resource, or its value when `flags` has no data bit.
+    pub data: u32,
+}
+
+impl Resource {
+    pub const LOWRES_IMAGE: [u8; 3] = [0x01, 0, 0];
+    pub const HIGHRES_IMAGE: [u8; 3] = [0x30, 0, 0];
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VtfHeader {
+    pub version: (u32, u32),
+    pub header_size: u32,
+    pub width: u16,
+    pub height: u16,
+    pub flags: u32,
+    pub frames: u16,
+    pub first_frame: u16,
+    pub reflectivity: [f32; 3],
+    pub bumpmap_scale: f32,
+    pub format: Format,
+    pub mipmap_count: u8,
+    pub lowres_format: Format,
+    pub lowres_width: u8,
+    pub lowres_height: u8,
+    /// Always 1 before 7.2.
+    pub depth: u16,
+    /// Always empty before 7.3.
+    pub resources: Vec<Resource>,
+}
+
+pub(crate) fn bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    data.get(offset..offset + N)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| anyhow!("Unexpected end of file at byte {}", offset))
+}
+
+pub(crate) fn u16_at(data: &[u8], offset: usize) -> Result<u16> {
+    bytes(data, offset).map(u16::from_le_bytes)
+}
+
+pub(crate) fn u32_at(data: &[u8], offset: usize) -> Result<u32> {
+    bytes(data, offset).map(u32::from_le_bytes)
+}
+
+fn i32_at(data: &[u8], offset: usize) -> Result<i32> {
+    bytes(data, offset).map(i32::from_le_bytes)
+}
+
+fn f32_at(data: &[u8], offset: usize) -> Result<f32> {
+    bytes(data, offset).map(f32::from_le_bytes)
+}
+
+impl VtfHeader {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if bytes::<4>(data, 0)? != *b"VTF\0" {
+            bail!("Not a VTF file");
+        }
+
+        let version = (u32_at(data, 4)?, u32_at(data, 8)?);
+
+        if version.0 != 7 || version.1 > 5 {
+            bail!("Unsupported VTF version {}.{}", version.0, version.1);
+        }
+
+        let (width, height) = (u16_at(data, 16)?, u16_at(data, 18)?);
+
+        if width == 0 || height == 0 {
+            bail!("Invalid size {}x{}", width, height);
+        }
+
+        let depth = if version.1 >= 2 { u16_at(data, 63)? } else { 1 };
+        let mipmap_count = bytes::<1>(data, 56)?[0];
+
+        // Each mipmap halves the largest side, down to a single pixel.
+        let max_mipmaps = u16::BITS - width.max(height).max(depth).leading_zeros();
+
+        if mipmap_count as u32 > max_mipmaps {
+            bail!(
+                "{} mipmaps is too many for {}x{}",
+                mipmap_count,
+                width,
+                height
+            );
+        }
+
+        let mut resources = Vec::new();
+
+        if version.1 >= 3 {
+            let count = u32_at(data, 68)?;
+
+            for i in 0..count as usize {
+                let offset = 80 + i * 8;
+
+                resources.push(Resource {
+                    tag: bytes(data, offset)?,
+                    flags: bytes::<1>(data, offset + 3)?[0],
+                    data: u32_at(data, offset + 4)?,
+                });
+            }
+        }
+
+        Ok(Self {
+            version,
+            header_size: u32_at(data, 12)?,
+            width,
+            height,
+            flags: u32_at(data, 20)?,
+            frames: u16_at(data, 24)?,
+            first_frame: u16_at(data, 26)?,
+            reflectivity: [f32_at(data, 32)?, f32_at(data, 36)?, f32_at(data, 40)?],
+            bumpmap_scale: f32_at(data, 48)?,
+            format: Format::from_i32(i32_at(data, 52)?)?,
+            mipmap_count,
+            lowres_format: Format::from_i32(i32_at(data, 57)?)?,
+            lowres_width: bytes::<1>(data, 61)?[0],
+            lowres_height: bytes::<1>(data, 62)?[0],
+            depth: depth.max(1),
+            resources,
+        })
+    }
+
+    /// Whether the image can be anything but fully opaque. DXT1 has punch-through alpha when
+    /// flagged with one-bit alpha.
+    pub fn has_alpha(&self) -> bool {
+        self.format.has_alpha()
+            || (self.format == Format::Dxt1 && self.flags & flags::ONE_BIT_ALPHA != 0)
+    }
+
+    fn resource(&self, tag: [u8; 3]) -> Option<&Resource> {
+        self.resources.iter().find(|r| r.tag == tag)
+    }
+
+    /// Cube maps store 7 faces, the last one a sphere map, unless made by 7.5 or later or with
+    /// `first_frame` set to -1.
+    fn faces(&self) -> usize {
+        if self.flags & flags::ENVMAP == 0 {
+            1
+        } else if self.version.1 < 5 && self.first_frame != 0xffff {
+            7
+        } else {
+            6
+        }
+    }
+
+    /// Offset of the first frame of the full size image. Mipmaps are stored smallest first, each
+    /// holding every frame, then every face, then every slice.
+    fn highres_offset(&self) -> Result<usize> {
+        let start = if self.version.1 >= 3 {
+            self.resource(Resource::HIGHRES_IMAGE)
+                .ok_or_else(|| anyhow!("Missing high-res image resource"))?
+                .data as usize
+        } else {
+            let lowres_size = match self.lowres_format {
+                Format::None => 0,
+                f => f
+                    .data_size(self.lowres_width as usize, self.lowres_height as usize)
+                    .ok_or_else(|| anyhow!("Unsupported low-res format {}", f.to_str()))?,
+            };
+
+            self.header_size as usize + lowres_size
+        };
+
+        let mut smaller_mipmaps = 0;
+
+        for mipmap in 1..self.mipmap_count.max(1) as u32 {
+            let (width, height, depth) = (
+                (self.width as usize >> mipmap).max(1),
+                (self.height as usize >> mipmap).max(1),
+                (self.depth as usize >> mipmap).max(1),
+            );
+
+            let size = self
+                .format
+                .data_size(width, height)
+                .ok_or_else(|| anyhow!("Decoding {} isn't supported", self.format.to_str()))?;
+
+            smaller_mipmaps += size * self.frames.max(1) as usize * self.faces() * depth;
+        }
+
+        Ok(start + smaller_mipmaps)
+    }
+
+    /// Offset of the low-res image, which comes right after the header before 7.3.
+    fn lowres_offset(&self) -> Option<usize> {
+        if self.version.1 >= 3 {
+            self.resource(Resource::LOWRES_IMAGE)
+                .map(|r| r.data as usize)
+        } else {
+            Some(self.header_size as usize)
+        }
+    }
+}
+
+/// Expands a channel of `bits` bits to 8 bits.
+fn expand(value: u32, bits: u32) -> u8 {
+    let max = (1 << bits) - 1;
+
+    ((value & max) * 255 / max) as u8
+}
+
+/// Splits a packed pixel into channels, `layout` being the bits of each channel starting from the
+/// lowest bits, in the order they are named in.
+fn unpack(value: u32, layout: &[u32]) -> Vec<u8> {
+    let mut shift = 0;
+
+    layout
+        .iter()
+        .map(|bits| {
+            let channel = expand(value >> shift, *bits);
+            shift += bits;
+            channel
+        })
+        .collect()
+}
+
+fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+
+    sign * match exponent {
+        0 => mantissa / 1024.0 * 2f32.powi(-14),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        e => (1.0 + mantissa / 1024.0) * 2f32.powi(e - 15),
+    }
+}
+
+fn rgb565(c: u16) -> [u8; 3] {
+    let c = unpack(c as u32, &[5, 6, 5]);
+
+    [c[2], c[1], c[0]]
+}
+
+/// The 4 colors of a DXT color block, where DXT1 uses 3 colors and transparent black when the
+/// first color isn't greater than the second.
+fn dxt_palette(block: &[u8], dxt1: bool) -> [[u8; 4]; 4] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (rgb0, rgb1) = (rgb565(c0), rgb565(c1));
+
+    let mix = |a: u8, b: u8, wa: u16, wb: u16| ((a as u16 * wa + b as u16 * wb) / (wa + wb)) as u8;
+
+    let mut palette = [[0; 4]; 4];
+    palette[0] = [rgb0[0], rgb0[1], rgb0[2], 255];
+    palette[1] = [rgb1[0], rgb1[1], rgb1[2], 255];
+
+    if c0 > c1 || !dxt1 {
+        for i in 0..3 {
+            palette[2][i] = mix(rgb0[i], rgb1[i], 2, 1);
+            palette[3][i] = mix(rgb0[i], rgb1[i], 1, 2);
+        }
+        palette[3][3] = 255;
+    } else {
+        for i in 0..3 {
+            palette[2][i] = mix(rgb0[i], rgb1[i], 1, 1);
+        }
+    }
+    palette[2][3] = 255;
+
+    palette
+}
+
+/// Alpha of the 16 pixels of a DXT5 alpha block.
+fn dxt5_alpha(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u16, block[1] as u16);
+
+    let mut palette = [0; 8];
+    palette[0] = a0 as u8;
+    palette[1] = a1 as u8;
+
+    if a0 > a1 {
+        for i in 1..7 {
+            palette[i + 1] = (((7 - i) as u16 * a0 + i as u16 * a1) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (((5 - i) as u16 * a0 + i as u16 * a1) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let mut indices = [0; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+
+    let mut alpha = [0; 16];
+    for (i, a) in alpha.iter_mut().enumerate() {
+        *a = palette[((indices >> (3 * i)) & 7) as usize];
+    }
+
+    alpha
+}
+
+fn decode_dxt(format: Format, data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let block_size = if matches!(format, Format::Dxt1 | Format::Dxt1OneBitAlpha) {
+        8
+    } else {
+        16
+    };
+    let blocks_x = width.div_ceil(4);
+
+    let mut rgba = vec![0; width * height * 4];
+
+    for (i, block) in data.chunks_exact(block_size).enumerate() {
+        let (bx, by) = (i % blocks_x, i / blocks_x);
+
+        let (alpha, color) = match format {
+            Format::Dxt3 => {
+                let mut alpha = [0; 16];
+                for (i, a) in alpha.iter_mut().enumerate() {
+                    *a = expand((block[i / 2] >> (4 * (i % 2))) as u32, 4);
+                }
+
+                (Some(alpha), &block[8..])
+            }
+            Format::Dxt5 => (Some(dxt5_alpha(block)), &block[8..]),
+            _ => (None, block),
+        };
+
+        let palette = dxt_palette(color, alpha.is_none());
+        let indices = u32::from_le_bytes([color[4], color[5], color[6], color[7]]);
+
+        for p in 0..16 {
+            let (x, y) = (bx * 4 + p % 4, by * 4 + p / 4);
+
+            if x >= width || y >= height {
+                continue;
+            }
+
+            let mut pixel = palette[((indices >> (2 * p)) & 3) as usize];
+            if let Some(alpha) = alpha {
+                pixel[3] = alpha[p];
+            }
+
+            let offset = (y * width + x) * 4;
+            rgba[offset..offset + 4].copy_from_slice(&pixel);
+        }
+    }
+
+    rgba
+}
+
+/// Decodes `width` by `height` pixels of `format` to RGBA.
+fn decode_format(format: Format, data: &[u8], width: usize, height: usize) -> Result<Vec<u8>> {
+    let size = format
+        .data_size(width, height)
+        .ok_or_else(|| anyhow!("Decoding {} isn't supported", format.to_str()))?;
+
+    let data = data
+        .get(..size)
+        .ok_or_else(|| anyhow!("Image data is too short"))?;
+
+    if format.is_compressed() {
+        return Ok(decode_dxt(format, data, width, height));
+    }
+
+    let bytes_per_pixel = size / (width * height);
+    let mut rgba = Vec::with_capacity(width * height * 4);
+
+    for p in data.chunks_exact(bytes_per_pixel) {
+        let packed = || u16::from_le_bytes([p[0], p[1]]) as u32;
+        let wide = |i: usize| (u16::from_le_bytes([p[i * 2], p[i * 2 + 1]]) >> 8) as u8;
+        let half = |i: usize| {
+            let value = half_to_f32(u16::from_le_bytes([p[i * 2], p[i * 2 + 1]]));
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+
+        let pixel = match format {
+            Format::Rgba8888 | Format::Uvwq8888 | Format::Uvlx8888 => [p[0], p[1], p[2], p[3]],
+            Format::Abgr8888 => [p[3], p[2], p[1], p[0]],
+            Format::Rgb888 => [p[0], p[1], p[2], 255],
+            Format::Bgr888 => [p[2], p[1], p[0], 255],
+            Format::Rgb888Bluescreen | Format::Bgr888Bluescreen => {
+                let rgb = if format == Format::Rgb888Bluescreen {
+                    [p[0], p[1], p[2]]
+                } else {
+                    [p[2], p[1], p[0]]
+                };
+
+                if rgb == [0, 0, 255] {
+                    [0, 0, 0, 0]
+                } else {
+                    [rgb[0], rgb[1], rgb[2], 255]
+                }
+            }
+            Format::Argb8888 => [p[1], p[2], p[3], p[0]],
+            Format::Bgra8888 => [p[2], p[1], p[0], p[3]],
+            Format::Bgrx8888 => [p[2], p[1], p[0], 255],
+            Format::Rgb565 => {
+                let c = unpack(packed(), &[5, 6, 5]);
+                [c[0], c[1], c[2], 255]
+            }
+            Format::Bgr565 => {
+                let c = unpack(packed(), &[5, 6, 5]);
+                [c[2], c[1], c[0], 255]
+            }
+            Format::Bgrx5551 => {
+                let c = unpack(packed(), &[5, 5, 5, 1]);
+                [c[2], c[1], c[0], 255]
+            }
+            Format::Bgra5551 => {
+                let c = unpack(packed(), &[5, 5, 5, 1]);
+                [c[2], c[1], c[0], c[3]]
+            }
+            Format::Bgra4444 => {
+                let c = unpack(packed(), &[4, 4, 4, 4]);
+                [c[2], c[1], c[0], c[3]]
+            }
+            Format::I8 => [p[0], p[0], p[0], 255],
+            Format::Ia88 => [p[0], p[0], p[0], p[1]],
+            Format::A8 => [0, 0, 0, p[0]],
+            Format::Uv88 => [p[0], p[1], 0, 255],
+            Format::Rgba16161616 => [wide(0), wide(1), wide(2), wide(3)],
+            Format::Rgba16161616F => [half(0), half(1), half(2), half(3)],
+            _ => bail!("Decoding {} isn't supported", format.to_str()),
+        };
+
+        rgba.extend_from_slice(&pixel);
+    }
+
+    Ok(rgba)
+}
+
+/// Everything in a VTF that matters for how a crosshair looks in game.
+pub struct TextureInfo {
+    pub header: VtfHeader,
+    pub lowres: Option<image::RgbaImage>,
+    pub warnings: Vec<String>,
+}
+
+impl TextureInfo {
+    pub fn new(data: &[u8]) -> Result<Self> {
+        let header = VtfHeader::parse(data)?;
+
+        let lowres = match header.lowres_offset() {
+            Some(offset) if header.lowres_width > 0 && header.lowres_height > 0 => {
+                let (width, height) = (header.lowres_width as usize, header.lowres_height as usize);
+
+                data.get(offset..)
+                    .and_then(|d| decode_format(header.lowres_format, d, width, height).ok())
+                    .and_then(|rgba| image::RgbaImage::from_raw(width as u32, height as u32, rgba))
+            }
+            _ => None,
+        };
+
+        let warnings = Self::warnings(&header);
+
+        Ok(Self {
+            header,
+            lowres,
+            warnings,
+        })
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = fs::read(path).with_context(|| {
+            format!(
+                "Failed to open {}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            )
+        })?;
+
+        Self::new(&data)
+    }
+
+    fn warnings(header: &VtfHeader) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let has_flag = |f| header.flags & f != 0;
+
+        if !header.has_alpha() {
+            warnings.push(format!(
+                "{} has no alpha channel, so the crosshair is drawn over a black box",
+                header.format.to_str()
+            ));
+        } else if header.format == Format::Dxt1OneBitAlpha || has_flag(flags::ONE_BIT_ALPHA) {
+            warnings.push("One-bit alpha gives the crosshair jagged edges".into());
+        }
+
+        if header.format.is_compressed() {
+            warnings.push(format!(
+                "{} compression blurs and discolors thin lines",
+                header.format.to_str()
+            ));
+        }
+
+        if header.mipmap_count > 1 && !has_flag(flags::NO_MIP) {
+            warnings.push(
+                "Has mipmaps without `No mip`, so it blurs when drawn smaller than its size".into(),
+            );
+        }
+
+        if !has_flag(flags::NO_LOD) {
+            warnings.push("Without `No LOD` lower texture quality settings blur it".into());
+        }
+
+        if !has_flag(flags::CLAMP_S) || !has_flag(flags::CLAMP_T) {
+            warnings.push(
+                "Without `Clamp S` and `Clamp T` filtering can bleed one edge into the other"
+                    .into(),
+            );
+        }
+
+        if !header.width.is_power_of_two() || !header.height.is_power_of_two() {
+            warnings.push(format!(
+                "{}x{} isn't a power of two, so it gets resampled",
+                header.width, header.height
+            ));
+        }
+
+        if header.frames > 1 {
+            warnings.push(format!(
+                "Has {} frames, only the first one is shown",
+                header.frames
+            ));
+        }
+
+        warnings
+    }
+}
+
+/// Decodes the first frame, face and slice of the full size image of a VTF.
+pub fn decode_data(data: &[u8]) -> Result<image::RgbaImage> {
+    let header = VtfHeader::parse(data)?;
+    let offset = header.highres_offset()?;
+
+    let (width, height) = (header.width as usize, header.height as usize);
+
+    let rgba = decode_format(
+        header.format,
+        data.get(offset..)
+            .ok_or_else(|| anyhow!("Image data is out of bounds"))?,
+        width,
+        height,
+    )?;
+
+    image::RgbaImage::from_raw(width as u32, height as u32, rgba)
+        .ok_or_else(|| anyhow!("Image data is too short"))
+}
+
+pub fn decode(path: &Path) -> Result<image::RgbaImage> {
+    let data = fs::read(path).with_context(|| {
+        format!(
+            "Failed to open {}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        )
+    })?;
+
+    decode_data(&data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A 7.2 header followed by a 4x4 DXT1 low-res image.
+    fn vtf(flags: u32, format: i32) -> Vec<u8> {
+        let mut data = vec![0; 80];
+        data[0..4].copy_from_slice(b"VTF\0");
+        data[4..8].copy_from_slice(&7u32.to_le_bytes());
+        data[8..12].copy_from_slice(&2u32.to_le_bytes());
+        data[12..16].copy_from_slice(&80u32.to_le_bytes());
+        data[16..18].copy_from_slice(&64u16.to_le_bytes());
+        data[18..20].copy_from_slice(&64u16.to_le_bytes());
+        data[20..24].copy_from_slice(&flags.to_le_bytes());
+        data[24..26].copy_from_slice(&1u16.to_le_bytes());
+        data[32..36].copy_from_slice(&0.5f32.to_le_bytes());
+        data[52..56].copy_from_slice(&format.to_le_bytes());
+        data[56] = 1;
+        data[57..61].copy_from_slice(&13i32.to_le_bytes());
+        data[61] = 4;
+        data[62] = 4;
+        data[63..65].copy_from_slice(&1u16.to_le_bytes());
+
+        // Pure red and pure blue, with the top row red and the rest blue.
+        data.extend_from_slice(&0xf800u16.to_le_bytes());
+        data.extend_from_slice(&0x001fu16.to_le_bytes());
+        data.extend_from_slice(&0x5555_5500u32.to_le_bytes());
+
+        data
+    }
+
+    /// A 7.2 VTF of a `width` by `height` `format` image, without mipmaps or a low-res image.
+    fn single(format: Format, width: u16, height: u16, image: &[u8]) -> Vec<u8> {
+        let format = Format::ALL.iter().position(|f| *f == format).unwrap() as i32;
+
+        let mut data = vec![0; 80];
+        data[0..4].copy_from_slice(b"VTF\0");
+        data[4..8].copy_from_slice(&7u32.to_le_bytes());
+        data[8..12].copy_from_slice(&2u32.to_le_bytes());
+        data[12..16].copy_from_slice(&80u32.to_le_bytes());
+        data[16..18].copy_from_slice(&width.to_le_bytes());
+        data[18..20].copy_from_slice(&height.to_le_bytes());
+        data[24..26].copy_from_slice(&1u16.to_le_bytes());
+        data[52..56].copy_from_slice(&format.to_le_bytes());
+        data[56] = 1;
+        data[57..61].copy_from_slice(&(-1i32).to_le_bytes());
+        data[63..65].copy_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(image);
+
+        data
+    }
+
+    /// A 2x2 RGBA8888 image with 2 mipmaps and a DXT1 low-res image, the full size mipmap holding
+    /// pixels 1 to 16 and the smaller one pixel 9.
+    fn mipmapped(minor: u32, flags: u32) -> Vec<u8> {
+        let header_size = match minor {
+            0 | 1 => 64,
+            2 => 80,
+            _ => 96,
+        };
+
+        let mut data = vec![0; header_size];
+        data[0..4].copy_from_slice(b"VTF\0");
+        data[4..8].copy_from_slice(&7u32.to_le_bytes());
+        data[8..12].copy_from_slice(&minor.to_le_bytes());
+        data[12..16].copy_from_slice(&(header_size as u32).to_le_bytes());
+        data[16..18].copy_from_slice(&2u16.to_le_bytes());
+        data[18..20].copy_from_slice(&2u16.to_le_bytes());
+        data[20..24].copy_from_slice(&flags.to_le_bytes());
+        data[24..26].copy_from_slice(&1u16.to_le_bytes());
+        data[56] = 2;
+        data[57..61].copy_from_slice(&13i32.to_le_bytes());
+        data[61] = 4;
+        data[62] = 4;
+
+        if minor >= 2 {
+            data[63..65].copy_from_slice(&1u16.to_le_bytes());
+        }
+
+        if minor >= 3 {
+            data[68..72].copy_from_slice(&2u32.to_le_bytes());
+            data[80..84].copy_from_slice(&[1, 0, 0, 0]);
+            data[84..88].copy_from_slice(&(header_size as u32).to_le_bytes());
+            data[88..92].copy_from_slice(&[0x30, 0, 0, 0]);
+            data[92..96].copy_from_slice(&(header_size as u32 + 8).to_le_bytes());
+        }
+
+        data.extend_from_slice(&[0; 8]);
+
+        let faces = TextureInfo::new(&data).unwrap().header.faces();
+        for _ in 0..faces {
+            data.extend_from_slice(&[9; 4]);
+        }
+        data.extend(1..=16);
+
+        data
+    }
+
+    #[test]
+    fn header() {
+        let info = TextureInfo::new(&vtf(
+            flags::NO_MIP | flags::NO_LOD | flags::CLAMP_S | flags::CLAMP_T,
+            12,
+        ))
+        .unwrap();
+
+        assert_eq!(info.header.version, (7, 2));
+        assert_eq!((info.header.width, info.header.height), (64, 64));
+        assert_eq!(info.header.format, Format::Bgra8888);
+        assert_eq!(info.header.lowres_format, Format::Dxt1);
+        assert_eq!(info.header.reflectivity[0], 0.5);
+        assert_eq!(
+            flags::names(info.header.flags),
+            vec!["Clamp S", "Clamp T", "No mip", "No LOD"]
+        );
+        assert!(info.warnings.is_empty());
+
+        let lowres = info.lowres.unwrap();
+        assert_eq!(lowres.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(lowres.get_pixel(3, 3).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn warnings() {
+        let info = TextureInfo::new(&vtf(0, 3)).unwrap();
+
+        assert!(info.warnings[0].starts_with("BGR888 has no alpha channel"));
+        assert_eq!(info.warnings.len(), 3);
+
+        let info = TextureInfo::new(&vtf(0, 13)).unwrap();
+        assert!(info.warnings[0].starts_with("DXT1 has no alpha channel"));
+
+        let info = TextureInfo::new(&vtf(flags::ONE_BIT_ALPHA, 13)).unwrap();
+        assert_eq!(
+            info.warnings[0],
+            "One-bit alpha gives the crosshair jagged edges"
+        );
+
+        assert!(TextureInfo::new(b"VTF\0").is_err());
+        assert!(TextureInfo::new(&vtf(0, 100)).is_err());
+
+        let mut empty = mipmapped(2, 0);
+        empty[16..18].copy_from_slice(&0u16.to_le_bytes());
+        assert!(TextureInfo::new(&empty).is_err());
+        assert!(decode_data(&empty).is_err());
+    }
+
+    #[test]
+    fn bundled() {
+        let info = TextureInfo::open(Path::new(
+            "resources/materials/vgui/replay/thumbnails/dot.vtf",
+        ))
+        .unwrap();
+
+        assert_eq!(info.header.format, Format::Dxt5);
+        assert_eq!((info.header.width, info.header.height), (64, 64));
+        assert!(info.lowres.is_some());
+
+        for file in fs::read_dir("resources/materials/vgui/replay/thumbnails").unwrap() {
+            let path = file.unwrap().path();
+
+            if path.extension() != Some("vtf".as_ref()) {
+                continue;
+            }
+
+            let header = TextureInfo::open(&path).unwrap().header;
+            let image = decode(&path).unwrap();
+
+            assert_eq!(
+                image.dimensions(),
+                (header.width as u32, header.height as u32)
+            );
+            assert!(image.pixels().any(|p| p.0[3] > 0), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn versions() {
+        for minor in 0..=5 {
+            let image = decode_data(&mipmapped(minor, 0)).unwrap();
+
+            assert_eq!(image.dimensions(), (2, 2));
+            assert_eq!(image.get_pixel(0, 0).0, [1, 2, 3, 4]);
+            assert_eq!(image.get_pixel(1, 1).0, [13, 14, 15, 16]);
+        }
+
+        // Cube maps before 7.5 have a 7th sphere map face.
+        for minor in [2, 5] {
+            let image = decode_data(&mipmapped(minor, flags::ENVMAP)).unwrap();
+            assert_eq!(image.get_pixel(0, 0).0, [1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn formats() {
+        let pixels: &[(Format, &[u8], [u8; 4])] = &[
+            (Format::Rgba8888, &[1, 2, 3, 4], [1, 2, 3, 4]),
+            (Format::Abgr8888, &[4, 3, 2, 1], [1, 2, 3, 4]),
+            (Format::Argb8888, &[4, 1, 2, 3], [1, 2, 3, 4]),
+            (Format::Bgra8888, &[3, 2, 1, 4], [1, 2, 3, 4]),
+            (Format::Uvwq8888, &[1, 2, 3, 4], [1, 2, 3, 4]),
+            (Format::Uvlx8888, &[1, 2, 3, 4], [1, 2, 3, 4]),
+            (Format::Bgrx8888, &[3, 2, 1, 0], [1, 2, 3, 255]),
+            (Format::Rgb888, &[1, 2, 3], [1, 2, 3, 255]),
+            (Format::Bgr888, &[3, 2, 1], [1, 2, 3, 255]),
+            (Format::Rgb888Bluescreen, &[0, 0, 255], [0, 0, 0, 0]),
+            (Format::Bgr888Bluescreen, &[255, 0, 0], [0, 0, 0, 0]),
+            (Format::Bgr888Bluescreen, &[3, 2, 1], [1, 2, 3, 255]),
+            (Format::I8, &[7], [7, 7, 7, 255]),
+            (Format::Ia88, &[7, 8], [7, 7, 7, 8]),
+            (Format::A8, &[8], [0, 0, 0, 8]),
+            (Format::Uv88, &[1, 2], [1, 2, 0, 255]),
+            (Format::Rgb565, &[0x1f, 0x00], [255, 0, 0, 255]),
+            (Format::Bgr565, &[0x1f, 0x00], [0, 0, 255, 255]),
+            (Format::Bgrx5551, &[0x1f, 0x00], [0, 0, 255, 255]),
+            (Format::Bgra5551, &[0x1f, 0x80], [0, 0, 255, 255]),
+            (Format::Bgra4444, &[0x0f, 0x80], [0, 0, 255, 136]),
+            (
+                Format::Rgba16161616,
+                &[0x00, 0x80, 0xff, 0xff, 0x00, 0x00, 0x00, 0x10],
+                [128, 255, 0, 16],
+            ),
+            (
+                Format::Rgba16161616F,
+                &[0x00, 0x38, 0x00, 0x3c, 0x00, 0x00, 0x00, 0x40],
+                [128, 255, 0, 255],
+            ),
+        ];
+
+        for (format, data, rgba) in pixels {
+            let image = decode_data(&single(*format, 1, 1, data)).unwrap();
+
+            assert_eq!(image.dimensions(), (1, 1));
+            assert_eq!(&image.get_pixel(0, 0).0, rgba, "{}", format.to_str());
+        }
+
+        // Pure red, pure blue, then the color indices.
+        let red = [0x00, 0xf8, 0x1f, 0x00, 0, 0, 0, 0];
+        let transparent = [0x1f, 0x00, 0x00, 0xf8, 0xff, 0xff, 0xff, 0xff];
+
+        let blocks: &[(Format, Vec<u8>, [u8; 4])] = &[
+            (Format::Dxt1, red.to_vec(), [255, 0, 0, 255]),
+            (Format::Dxt1OneBitAlpha, transparent.to_vec(), [0, 0, 0, 0]),
+            (Format::Dxt3, [[0x88; 8], red].concat(), [255, 0, 0, 136]),
+            (
+                Format::Dxt5,
+                [[200, 0, 0, 0, 0, 0, 0, 0], red].concat(),
+                [255, 0, 0, 200],
+            ),
+        ];
+
+        for (format, data, rgba) in blocks {
+            let image = decode_data(&single(*format, 4, 4, data)).unwrap();
+
+            assert_eq!(image.dimensions(), (4, 4));
+            assert!(image.pixels().all(|p| p.0 == *rgba), "{}", format.to_str());
+        }
+
+        // More mipmaps than halving the size allows.
+        let mut mipmaps = single(Format::Rgba8888, 4, 4, &[0; 84]);
+        mipmaps[56] = 3;
+        assert!(decode_data(&mipmaps).is_ok());
+        mipmaps[56] = 4;
+        assert!(decode_data(&mipmaps).is_err());
+        mipmaps[56] = 64;
+        assert!(TextureInfo::new(&mipmaps).is_err());
+
+        assert!(decode_data(&single(Format::P8, 1, 1, &[0])).is_err());
+        assert!(decode_data(&single(Format::Rgba8888, 1, 1, &[0; 3])).is_err());
+    }
+}
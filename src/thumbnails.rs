@@ -0,0 +1,265 @@
+use crate::texture;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+/// Width and height thumbnails are scaled down to, keeping their aspect ratio.
+pub const SIZE: u32 = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub png: Vec<u8>,
+    /// Size of the full texture, not of the thumbnail.
+    pub size: (i32, i32),
+}
+
+impl Thumbnail {
+    pub fn from_vtf(path: &Path) -> Result<Self> {
+        let image = texture::decode(path)?;
+        let size = (image.width() as i32, image.height() as i32);
+
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .resize(SIZE, SIZE, image::FilterType::Triangle)
+            .write_to(&mut png, image::ImageFormat::PNG)?;
+
+        Ok(Self { png, size })
+    }
+}
+
+/// Thumbnails stored as `<key>-<width>x<height>.png`, keyed on the name, length and
+/// modification time of the VTF they were made from.
+pub struct Cache {
+    dir: PathBuf,
+    entries: HashMap<u64, ((i32, i32), PathBuf)>,
+}
+
+impl Cache {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create folder `{}`", dir.display()))?;
+
+        let mut entries = HashMap::new();
+
+        for file in fs::read_dir(dir)
+            .with_context(|| format!("Failed to read folder `{}`", dir.display()))?
+        {
+            let path = file?.path();
+
+            if let Some((key, size)) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(Self::parse_name)
+            {
+                entries.insert(key, (size, path));
+            }
+        }
+
+        Ok(Self {
+            dir: dir.into(),
+            entries,
+        })
+    }
+
+    fn parse_name(name: &str) -> Option<(u64, (i32, i32))> {
+        let (key, size) = name.split_once('-')?;
+        let (width, height) = size.split_once('x')?;
+
+        Some((
+            u64::from_str_radix(key, 16).ok()?,
+            (width.parse().ok()?, height.parse().ok()?),
+        ))
+    }
+
+    /// A SHA-256 of the VTF's name, length and modification time, so keys stay the same
+    /// between builds.
+    pub fn key(vtf_path: &Path) -> Result<u64> {
+        let metadata = fs::metadata(vtf_path)?;
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(
+            vtf_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .as_bytes(),
+        );
+        hasher.update(metadata.len().to_le_bytes());
+        hasher.update(modified.as_nanos().to_le_bytes());
+
+        let mut key = [0; 8];
+        key.copy_from_slice(&hasher.finalize()[..8]);
+
+        Ok(u64::from_le_bytes(key))
+    }
+
+    /// Removes thumbnails of VTFs that are gone or have changed since, returning how many.
+    pub fn prune(&mut self, vtf_paths: &[PathBuf]) -> Result<usize> {
+        let keys = vtf_paths
+            .iter()
+            .filter_map(|p| Self::key(p).ok())
+            .collect::<HashSet<_>>();
+
+        let stale = self
+            .entries
+            .keys()
+            .filter(|k| !keys.contains(k))
+            .copied()
+            .collect::<Vec<_>>();
+
+        for key in &stale {
+            if let Some((_, path)) = self.entries.remove(key) {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+
+        Ok(stale.len())
+    }
+
+    pub fn get(&self, key: u64) -> Option<Thumbnail> {
+        let (size, path) = self.entries.get(&key)?;
+
+        Some(Thumbnail {
+            png: fs::read(path).ok()?,
+            size: *size,
+        })
+    }
+
+    pub fn insert(&self, key: u64, thumbnail: &Thumbnail) -> Result<()> {
+        let path = self.dir.join(format!(
+            "{:016x}-{}x{}.png",
+            key, thumbnail.size.0, thumbnail.size.1
+        ));
+
+        fs::write(&path, &thumbnail.png)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+fn load(path: &Path, cache: Option<&Cache>) -> Result<Thumbnail> {
+    let cache = cache.and_then(|c| Cache::key(path).ok().map(|key| (c, key)));
+
+    if let Some(thumbnail) = cache.and_then(|(c, key)| c.get(key)) {
+        return Ok(thumbnail);
+    }
+
+    let thumbnail = Thumbnail::from_vtf(path)?;
+
+    if let Some((c, key)) = cache {
+        // A cache that can't be written to only costs decoding again next launch.
+        let _ = c.insert(key, &thumbnail);
+    }
+
+    Ok(thumbnail)
+}
+
+/// Makes thumbnails of `paths` on one thread per core, calling `f` as each one finishes.
+pub fn load_all<F>(paths: &[PathBuf], cache: Option<&Cache>, f: F)
+where
+    F: Fn(&Path, Result<Thumbnail>) + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .min(paths.len());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(path) = paths.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    f(path, load(path, cache));
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    #[test]
+    fn cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let thumbnail = Thumbnail {
+            png: vec![1, 2, 3],
+            size: (64, 32),
+        };
+
+        Cache::open(temp_dir.path())
+            .unwrap()
+            .insert(42, &thumbnail)
+            .unwrap();
+
+        let cache = Cache::open(temp_dir.path()).unwrap();
+
+        assert_eq!(cache.get(42), Some(thumbnail));
+        assert_eq!(cache.get(43), None);
+    }
+
+    #[test]
+    fn prune() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let vtf = temp_dir.path().join("dot.vtf");
+        fs::write(&vtf, [0; 4]).unwrap();
+
+        let cache_dir = temp_dir.path().join("cache");
+        let cache = Cache::open(&cache_dir).unwrap();
+
+        let key = Cache::key(&vtf).unwrap();
+        assert_eq!(key, Cache::key(&vtf).unwrap());
+
+        let thumbnail = Thumbnail {
+            png: vec![1, 2, 3],
+            size: (32, 32),
+        };
+        cache.insert(key, &thumbnail).unwrap();
+        cache.insert(key ^ 1, &thumbnail).unwrap();
+
+        let mut cache = Cache::open(&cache_dir).unwrap();
+        assert_eq!(cache.prune(&[vtf]).unwrap(), 1);
+
+        assert_eq!(cache.get(key), Some(thumbnail));
+        assert_eq!(cache.get(key ^ 1), None);
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn load_all() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(temp_dir.path()).unwrap();
+
+        let paths = ["cpma_1.vtf", "ql_1.vtf", "dot.vtf"]
+            .map(|f| Path::new("resources/materials/vgui/replay/thumbnails").join(f));
+
+        let loaded = Mutex::new(Vec::new());
+
+        super::load_all(&paths, Some(&cache), |path, thumbnail| {
+            loaded
+                .lock()
+                .unwrap()
+                .push((path.to_path_buf(), thumbnail.unwrap()));
+        });
+
+        let loaded = loaded.into_inner().unwrap();
+        assert_eq!(loaded.len(), paths.len());
+
+        let cache = Cache::open(temp_dir.path()).unwrap();
+
+        for (path, thumbnail) in loaded {
+            assert_eq!(cache.get(Cache::key(&path).unwrap()), Some(thumbnail));
+        }
+    }
+}
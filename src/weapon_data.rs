@@ -0,0 +1,177 @@
+use crate::keyvalues::{KeyValues, Value};
+
+use anyhow::{bail, Result};
+
+/// Stats of a weapon script's `WeaponData`, for picking a crosshair that suits the weapon. Any
+/// the script leaves out, or the game fills in elsewhere, are `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WeaponData {
+    pub damage: Option<i32>,
+    pub bullets_per_shot: Option<i32>,
+    /// Spread of each bullet, as the tangent of the cone's half angle.
+    pub spread: Option<f32>,
+    /// Seconds between shots.
+    pub time_fire_delay: Option<f32>,
+    pub time_reload: Option<f32>,
+    pub clip_size: Option<i32>,
+    pub range: Option<i32>,
+    pub projectile_type: Option<String>,
+    pub damage_radius: Option<i32>,
+    pub melee: bool,
+    /// Every text value of the block, for rules to match keys without a field of their own.
+    values: Vec<(String, String)>,
+}
+
+impl WeaponData {
+    pub fn parse(s: &str) -> Result<Self> {
+        let kv = KeyValues::parse(s)?;
+
+        // The game doesn't read the name of the block, and a stock script even misspells it.
+        let data = kv.0.iter().find_map(|(_, v)| match v {
+            Value::Block(b) => Some(b),
+            Value::Text(_) => None,
+        });
+
+        let data = match data {
+            Some(d) => d,
+            None => bail!("Expected a `WeaponData` block"),
+        };
+
+        let text = |key| data.text(key).map(str::trim);
+        let int = |key| text(key).and_then(|v| v.parse().ok());
+        let float = |key| text(key).and_then(|v| v.parse().ok());
+
+        Ok(Self {
+            damage: int("Damage"),
+            bullets_per_shot: int("BulletsPerShot"),
+            spread: float("Spread"),
+            time_fire_delay: float("TimeFireDelay"),
+            time_reload: float("TimeReload"),
+            // Clips of -1 mean the weapon fires straight from its ammo.
+            clip_size: int("clip_size").filter(|&c| c > 0),
+            range: int("Range"),
+            projectile_type: text("ProjectileType")
+                .filter(|p| !p.is_empty())
+                .map(str::to_string),
+            damage_radius: int("DamageRadius"),
+            melee: text("MeleeWeapon") == Some("1"),
+            values: data
+                .texts()
+                .map(|(k, v)| (k.to_string(), v.trim().to_string()))
+                .collect(),
+        })
+    }
+
+    /// Value of `key` in the script, ignoring case like the game does.
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The projectile without its `projectile_` prefix, as in "rocket" or "pipe remote".
+    pub fn projectile(&self) -> Option<String> {
+        let p = self.projectile_type.as_deref()?;
+        let p = p.strip_prefix("tf_").unwrap_or(p);
+        let p = p.strip_prefix("projectile_").unwrap_or(p);
+
+        Some(p.replace('_', " "))
+    }
+
+    /// Full angle of the spread cone, in degrees.
+    pub fn spread_degrees(&self) -> Option<f32> {
+        self.spread.map(|s| 2.0 * s.atan().to_degrees())
+    }
+
+    /// The stats the script has, one `Label: value` per line.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        if let Some(damage) = self.damage {
+            lines.push(match self.bullets_per_shot.filter(|&b| b > 1) {
+                Some(bullets) => format!("Damage: {} x {} bullets", damage, bullets),
+                None => format!("Damage: {}", damage),
+            });
+        }
+
+        if let (Some(spread), Some(degrees)) = (self.spread, self.spread_degrees()) {
+            if spread > 0.0 {
+                lines.push(format!("Spread: {} ({:.1}° cone)", spread, degrees));
+            }
+        }
+
+        if let Some(delay) = self.time_fire_delay {
+            lines.push(format!("Fire Delay: {}s", delay));
+        }
+
+        if let Some(clip_size) = self.clip_size {
+            lines.push(format!("Clip Size: {}", clip_size));
+        }
+
+        if let Some(reload) = self.time_reload {
+            lines.push(format!("Reload Time: {}s", reload));
+        }
+
+        if self.melee {
+            lines.push("Projectile: none (melee)".to_string());
+        } else if let Some(projectile) = self.projectile() {
+            lines.push(format!("Projectile: {}", projectile));
+        }
+
+        if let Some(radius) = self.damage_radius {
+            lines.push(format!("Blast Radius: {}", radius));
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+
+    fn script(name: &str) -> WeaponData {
+        let s = fs::read_to_string(format!("resources/scripts/{}.txt", name)).unwrap();
+        WeaponData::parse(&s).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let scattergun = script("tf_weapon_scattergun");
+
+        assert_eq!(scattergun.damage, Some(6));
+        assert_eq!(scattergun.bullets_per_shot, Some(10));
+        assert_eq!(scattergun.spread, Some(0.0675));
+        assert_eq!(scattergun.time_fire_delay, Some(0.625));
+        assert_eq!(scattergun.clip_size, Some(6));
+        assert_eq!(scattergun.projectile().as_deref(), Some("bullet"));
+        assert!(!scattergun.melee);
+
+        let rocket_launcher = script("tf_weapon_rocketlauncher");
+        assert_eq!(rocket_launcher.projectile().as_deref(), Some("rocket"));
+        assert_eq!(script("tf_weapon_grenadelauncher").damage_radius, Some(146));
+
+        assert!(script("tf_weapon_knife").melee);
+        assert_eq!(scattergun.value("weapontype"), Some("primary"));
+
+        // Every stock script parses.
+        for file in fs::read_dir("resources/scripts").unwrap() {
+            let path = file.unwrap().path();
+            WeaponData::parse(&fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        }
+    }
+
+    #[test]
+    fn lines() {
+        let lines = script("tf_weapon_scattergun").lines();
+
+        assert_eq!(lines[0], "Damage: 6 x 10 bullets");
+        assert_eq!(lines[1], "Spread: 0.0675 (7.7° cone)");
+        assert!(lines.contains(&"Projectile: bullet".to_string()));
+        assert!(WeaponData::default().lines().is_empty());
+    }
+}
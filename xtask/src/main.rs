@@ -15,6 +15,7 @@ fn run() -> Result<()> {
     let task = env::args().nth(1);
     match task.as_deref() {
         Some("dist") => dist()?,
+        Some("associations") => associations(env::args().nth(2))?,
         _ => print_help(),
     }
 
@@ -26,6 +27,9 @@ fn print_help() {
         "\
 TASKS:
     dist            Builds the binary and zips them with the files in `resources`
+    associations <items_game.txt>
+                    Regenerates `src/associations.json` from the game's item schema, for the
+                    weapon scripts in `resources/scripts`
 "
     )
 }
@@ -104,6 +108,16 @@ fn dist() -> Result<()> {
         Err("copying materials dir failed")?;
     }
 
+    // Lets the tool tell the files it installed apart from the user's, to uninstall them.
+    let status = Command::new(dist_dir().join(exe))
+        .current_dir(dist_dir())
+        .args(&["manifest", "dist"])
+        .status()?;
+
+    if !status.success() {
+        Err("writing install manifest failed")?;
+    }
+
     let zip_name = if cfg!(target_os = "windows") {
         "crosshair-switcher-windows.zip"
     } else if cfg!(target_os = "linux") {
@@ -126,6 +140,24 @@ fn dist() -> Result<()> {
     Ok(())
 }
 
+fn associations(items_game: Option<String>) -> Result<()> {
+    let items_game = items_game.ok_or("expected the path of `items_game.txt`")?;
+
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .current_dir(project_root())
+        .args(&["run", "--release", "--", "associations"])
+        .arg(fs::canonicalize(items_game)?)
+        .args(&["src/associations.json", "resources/scripts"])
+        .status()?;
+
+    if !status.success() {
+        Err("generating associations failed")?;
+    }
+
+    Ok(())
+}
+
 fn project_root() -> BasePathBuf {
     std::path::Path::new(&env!("CARGO_MANIFEST_DIR"))
         .parent()